@@ -1,6 +1,11 @@
 //! # Day 1: Secret Entrance
 //! Input is a list of dial rotations on a 0-99 circle starting at 50, each as `L|R<clicks>` on its
-//! own line.
+//! own line. A `RESET` line snaps the dial straight back to the start position without passing
+//! through any other position.
+//!
+//! A variant uses a continuous 360-unit dial starting halfway at 180, where clicks may be decimals
+//! like `L12.5`. As fractional positions accumulate rounding errors, a rotation counts as ending
+//! at 0 when it ends within a configurable epsilon of it on either side.
 //!
 //! ## Part A
 //! Apply rotations and count how many end with the dial at 0.
@@ -9,60 +14,205 @@
 //! Count every click that passes through 0 during rotations, including intermediate clicks on long
 //! moves.
 use anyhow::{Context, Result, bail};
+use std::str::FromStr;
+
+pub const TITLE: &str = "Secret Entrance";
 
 const DIAL_SIZE: usize = 100;
 const START_POS: usize = 50;
 
-#[derive(Debug, Clone, Copy)]
+/// Size of the continuous dial used by fractional rotations
+#[cfg(test)]
+const FRACTIONAL_DIAL_SIZE: f64 = 360.0;
+/// Default distance from 0 within which a fractional rotation counts as ending at 0
+#[cfg(test)]
+const FRACTIONAL_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Rotation {
     Left,
     Right,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Instruction {
-    dir: Rotation,
-    clicks: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction<C = usize> {
+    Turn { dir: Rotation, clicks: C },
+    Reset,
 }
 
 impl Instruction {
-    /// Advance the dial by this rotation and return the new position.
+    /// Advance the dial by this instruction and return the new position.
     fn rotate(self, position: usize) -> usize {
-        let delta = self.clicks % DIAL_SIZE;
-        match self.dir {
-            Rotation::Left => (position + DIAL_SIZE - delta) % DIAL_SIZE,
-            Rotation::Right => (position + delta) % DIAL_SIZE,
+        match self {
+            Instruction::Turn { dir, clicks } => {
+                let delta = clicks % DIAL_SIZE;
+                match dir {
+                    Rotation::Left => (position + DIAL_SIZE - delta) % DIAL_SIZE,
+                    Rotation::Right => (position + delta) % DIAL_SIZE,
+                }
+            }
+            Instruction::Reset => START_POS,
+        }
+    }
+
+    /// Return the number of clicks this instruction turns the dial.
+    #[cfg(test)]
+    fn clicks(self) -> usize {
+        match self {
+            Instruction::Turn { clicks, .. } => clicks,
+            Instruction::Reset => 0,
+        }
+    }
+}
+
+/// Size and start of a dial with clicks of type `C`
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+struct Dial<C = usize> {
+    size: C,
+    start: C,
+}
+
+#[cfg(test)]
+impl Default for Dial {
+    fn default() -> Self {
+        Self {
+            size: DIAL_SIZE,
+            start: START_POS,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Default for Dial<f64> {
+    fn default() -> Self {
+        Self {
+            size: FRACTIONAL_DIAL_SIZE,
+            start: FRACTIONAL_DIAL_SIZE / 2.0,
         }
     }
 }
 
-/// Parse strict rotation instructions of form `L|R<clicks>` into direction-click pairs.
-fn parse_input(input: &str) -> Result<Vec<Instruction>> {
+#[cfg(test)]
+impl Dial {
+    /// Return the direction and number of clicks of the shorter arc from `from` to `to`, turning
+    /// right when both arcs are equally long.
+    fn shortest_turn(self, from: usize, to: usize) -> (Rotation, usize) {
+        let right = (to + self.size - from % self.size) % self.size;
+        let left = (self.size - right) % self.size;
+        if left < right {
+            (Rotation::Left, left)
+        } else {
+            (Rotation::Right, right)
+        }
+    }
+}
+
+#[cfg(test)]
+impl Dial<f64> {
+    /// Check whether `position` is within `epsilon` of 0, approaching from either side.
+    fn is_zero(self, position: f64, epsilon: f64) -> bool {
+        position <= epsilon || self.size - position <= epsilon
+    }
+}
+
+#[cfg(test)]
+impl Instruction<f64> {
+    /// Advance the continuous dial by this instruction and return the new position.
+    fn rotate_fractional(self, position: f64, dial: Dial<f64>) -> f64 {
+        match self {
+            Instruction::Turn { dir, clicks } => match dir {
+                Rotation::Left => (position - clicks).rem_euclid(dial.size),
+                Rotation::Right => (position + clicks).rem_euclid(dial.size),
+            },
+            Instruction::Reset => dial.start,
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TravelStats {
+    total_clicks: usize,
+    max_excursion: usize,
+    final_pos: usize,
+}
+
+/// Parse a strict rotation instruction of form `L|R<clicks>` into a direction-click pair, or
+/// `RESET`.
+fn parse_instruction<C>(line: &str, line_no: usize) -> Result<Instruction<C>>
+where
+    C: FromStr<Err: std::error::Error + Send + Sync + 'static>,
+{
+    if line == "RESET" {
+        return Ok(Instruction::Reset);
+    }
+    let mut chars = line.chars();
+    let dir = match chars
+        .next()
+        .with_context(|| format!("Missing direction on line {}", line_no))?
+    {
+        'L' => Rotation::Left,
+        'R' => Rotation::Right,
+        other => bail!("Unknown direction {other} on line {}", line_no),
+    };
+
+    let clicks = chars
+        .as_str()
+        .parse()
+        .with_context(|| format!("Invalid click count on line {}", line_no))?;
+    Ok(Instruction::Turn { dir, clicks })
+}
+
+/// Parse one rotation instruction per line, passing the click count of every turn along with its
+/// line number to `validate`.
+fn parse_instructions<C>(
+    input: &str,
+    validate: impl Fn(C, usize) -> Result<()>,
+) -> Result<Vec<Instruction<C>>>
+where
+    C: Copy + FromStr<Err: std::error::Error + Send + Sync + 'static>,
+{
     input
         .trim()
         .lines()
         .enumerate()
         .map(|(idx, line)| {
             let line_no = idx + 1;
-            let mut chars = line.chars();
-            let dir = match chars
-                .next()
-                .with_context(|| format!("Missing direction on line {}", line_no))?
-            {
-                'L' => Rotation::Left,
-                'R' => Rotation::Right,
-                other => bail!("Unknown direction {other} on line {}", line_no),
-            };
-
-            let clicks = chars
-                .as_str()
-                .parse()
-                .with_context(|| format!("Invalid click count on line {}", line_no))?;
-            Ok(Instruction { dir, clicks })
+            let instruction = parse_instruction(line, line_no)?;
+            if let Instruction::Turn { clicks, .. } = instruction {
+                validate(clicks, line_no)?;
+            }
+            Ok(instruction)
         })
         .collect()
 }
 
+/// Parse strict rotation instructions of form `L|R<clicks>` into direction-click pairs, or `RESET`.
+/// Click counts above `max_clicks` are rejected when a cap is given.
+fn parse_input(input: &str, max_clicks: Option<usize>) -> Result<Vec<Instruction>> {
+    parse_instructions(input, |clicks, line_no| {
+        if let Some(max_clicks) = max_clicks
+            && clicks > max_clicks
+        {
+            bail!("Click count {clicks} exceeds limit of {max_clicks} on line {line_no}");
+        }
+        Ok(())
+    })
+}
+
+/// Parse rotation instructions like `parse_input`, but allow decimal click counts like `L12.5`.
+/// Click counts must be finite and not negative.
+#[cfg(test)]
+fn parse_fractional_input(input: &str) -> Result<Vec<Instruction<f64>>> {
+    parse_instructions(input, |clicks: f64, line_no| {
+        if !(clicks.is_finite() && clicks >= 0.0) {
+            bail!("Invalid click count {clicks} on line {line_no}");
+        }
+        Ok(())
+    })
+}
+
 /// Count how often the dial ends a rotation at 0 on a 0-99 circle starting from 50.
 fn part_a(rotations: &[Instruction]) -> usize {
     let mut position = START_POS;
@@ -75,26 +225,139 @@ fn part_a(rotations: &[Instruction]) -> usize {
         .sum()
 }
 
+/// Count how often a rotation of the continuous dial ends within `epsilon` of 0.
+#[cfg(test)]
+fn part_a_fractional(rotations: &[Instruction<f64>], dial: Dial<f64>, epsilon: f64) -> usize {
+    let mut position = dial.start;
+    rotations
+        .iter()
+        .filter(|instruction| {
+            position = instruction.rotate_fractional(position, dial);
+            dial.is_zero(position, epsilon)
+        })
+        .count()
+}
+
+/// Count how many clicks of a single rotation land on 0 when starting from `position`.
+fn crossings_in_move(position: usize, dir: Rotation, clicks: usize) -> usize {
+    let offset = match dir {
+        Rotation::Left => position,
+        Rotation::Right => DIAL_SIZE - position,
+    };
+    let clicks_to_zero = if offset == 0 { DIAL_SIZE } else { offset };
+    if clicks_to_zero <= clicks {
+        1 + (clicks - clicks_to_zero) / DIAL_SIZE
+    } else {
+        0
+    }
+}
+
 /// Count every click landing on 0 (including mid-rotation) across all rotations.
 fn part_b(rotations: &[Instruction]) -> usize {
     let mut position = START_POS;
     let mut hits = 0;
     for &instruction in rotations {
-        let offset = match instruction.dir {
-            Rotation::Left => position,
-            Rotation::Right => DIAL_SIZE - position,
-        };
-        let clicks_to_zero = if offset == 0 { DIAL_SIZE } else { offset };
-        if clicks_to_zero <= instruction.clicks {
-            hits += 1 + (instruction.clicks - clicks_to_zero) / DIAL_SIZE;
+        if let Instruction::Turn { dir, clicks } = instruction {
+            hits += crossings_in_move(position, dir, clicks);
         }
         position = instruction.rotate(position);
     }
     hits
 }
 
+/// Return the shortest distance around the dial between `position` and the start position.
+#[cfg(test)]
+fn distance_from_start(position: usize) -> usize {
+    let distance = position.abs_diff(START_POS);
+    distance.min(DIAL_SIZE - distance)
+}
+
+/// Return the signed displacement from the start position after each instruction, going the
+/// shorter way around the dial. Positions reached by turning left from the start are negative, so
+/// values lie within `-50..=49` on the 100-click dial.
+#[cfg(test)]
+fn displacement_series(rotations: &[Instruction]) -> Vec<isize> {
+    let mut position = START_POS;
+    rotations
+        .iter()
+        .map(|instruction| {
+            position = instruction.rotate(position);
+            let offset = (position + DIAL_SIZE - START_POS) % DIAL_SIZE;
+            if offset < DIAL_SIZE / 2 {
+                offset as isize
+            } else {
+                offset as isize - DIAL_SIZE as isize
+            }
+        })
+        .collect()
+}
+
+/// Sum all clicks regardless of direction, and track the farthest any click took the dial from the
+/// start position (measured around the circle) along with the final position.
+#[cfg(test)]
+fn travel_stats(rotations: &[Instruction]) -> TravelStats {
+    let mut position = START_POS;
+    let mut max_excursion = 0;
+    for &instruction in rotations {
+        // Any rotation of a full turn or more passes every position on the dial
+        let excursion = match instruction {
+            Instruction::Turn { dir, clicks } => (1..=clicks.min(DIAL_SIZE))
+                .map(|clicks| {
+                    distance_from_start(Instruction::Turn { dir, clicks }.rotate(position))
+                })
+                .max()
+                .unwrap_or(0),
+            Instruction::Reset => 0,
+        };
+        max_excursion = max_excursion.max(excursion);
+        position = instruction.rotate(position);
+    }
+
+    debug_assert!(max_excursion <= DIAL_SIZE / 2);
+    TravelStats {
+        total_clicks: rotations
+            .iter()
+            .map(|instruction| instruction.clicks())
+            .sum(),
+        max_excursion,
+        final_pos: position,
+    }
+}
+
+/// Return the dial position after applying the first `n` instructions, or all of them if there
+/// are fewer.
+#[cfg(test)]
+fn position_after(rotations: &[Instruction], n: usize) -> usize {
+    rotations
+        .iter()
+        .take(n)
+        .fold(START_POS, |position, instruction| {
+            instruction.rotate(position)
+        })
+}
+
+/// Return the fewest clicks needed to visit every target position in order, like entering a
+/// combination, along with the instructions that do it. Targets are taken modulo the dial size and
+/// a target the dial already points at needs no instruction.
+#[cfg(test)]
+fn min_clicks_to(targets: &[usize], dial: Dial) -> (usize, Vec<Instruction>) {
+    let mut position = dial.start;
+    let mut total = 0;
+    let mut instructions = Vec::new();
+    for &target in targets {
+        let target = target % dial.size;
+        let (dir, clicks) = dial.shortest_turn(position, target);
+        if clicks > 0 {
+            instructions.push(Instruction::Turn { dir, clicks });
+        }
+        total += clicks;
+        position = target;
+    }
+    (total, instructions)
+}
+
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let rotations = parse_input(input)?;
+    let rotations = parse_input(input, None)?;
     Ok((part_a(&rotations), Some(part_b(&rotations))))
 }
 
@@ -123,11 +386,150 @@ mod test {
 
     #[test]
     fn example_a() {
-        assert_eq!(part_a(&parse_input(EXAMPLE_INPUT).unwrap()), 3);
+        assert_eq!(part_a(&parse_input(EXAMPLE_INPUT, None).unwrap()), 3);
     }
 
     #[test]
     fn example_b() {
-        assert_eq!(part_b(&parse_input(EXAMPLE_INPUT).unwrap()), 6);
+        assert_eq!(part_b(&parse_input(EXAMPLE_INPUT, None).unwrap()), 6);
+    }
+
+    #[test]
+    fn crossings() {
+        assert_eq!(crossings_in_move(0, Rotation::Left, 0), 0);
+        assert_eq!(crossings_in_move(0, Rotation::Left, 99), 0);
+        assert_eq!(crossings_in_move(0, Rotation::Left, 100), 1);
+        assert_eq!(crossings_in_move(0, Rotation::Right, 100), 1);
+        assert_eq!(crossings_in_move(0, Rotation::Right, 250), 2);
+
+        assert_eq!(crossings_in_move(30, Rotation::Left, 29), 0);
+        assert_eq!(crossings_in_move(30, Rotation::Left, 30), 1);
+        assert_eq!(crossings_in_move(30, Rotation::Right, 30), 0);
+        assert_eq!(crossings_in_move(30, Rotation::Right, 70), 1);
+        assert_eq!(crossings_in_move(30, Rotation::Left, 100), 1);
+        assert_eq!(crossings_in_move(30, Rotation::Left, 130), 2);
+        assert_eq!(crossings_in_move(30, Rotation::Right, 269), 2);
+        assert_eq!(crossings_in_move(30, Rotation::Right, 270), 3);
+    }
+
+    #[test]
+    fn click_cap() {
+        assert!(parse_input(EXAMPLE_INPUT, Some(99)).is_ok());
+        let err = parse_input(EXAMPLE_INPUT, Some(98)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Click count 99 exceeds limit of 98 on line 8"
+        );
+        assert!(parse_input("R1000000", None).is_ok());
+    }
+
+    #[test]
+    fn reset() {
+        let rotations = parse_input("L50\nRESET\nR50\nRESET\nL150", None).unwrap();
+        assert_eq!(part_a(&rotations), 3);
+        assert_eq!(part_b(&rotations), 4);
+
+        let rotations = parse_input("L40\nRESET\nL20", None).unwrap();
+        assert_eq!(part_a(&rotations), 0);
+        assert_eq!(part_b(&rotations), 0);
+        assert_eq!(travel_stats(&rotations).final_pos, 30);
+        assert!(parse_input("RESET1", None).is_err());
+    }
+
+    #[test]
+    fn displacement() {
+        let series = displacement_series(&parse_input(EXAMPLE_INPUT, None).unwrap());
+        assert_eq!(series, vec![32, 2, -50, 45, 5, -50, 49, -50, -36, -18]);
+        assert_eq!(
+            displacement_series(&parse_input("R49\nL99\nRESET", None).unwrap()),
+            vec![49, -50, 0]
+        );
+    }
+
+    #[test]
+    fn position_after_prefix() {
+        let rotations = parse_input(EXAMPLE_INPUT, None).unwrap();
+        assert_eq!(position_after(&rotations, 0), 50);
+        assert_eq!(position_after(&rotations, 3), 0);
+        assert_eq!(position_after(&rotations, 5), 55);
+        assert_eq!(position_after(&rotations, 10), 32);
+        assert_eq!(position_after(&rotations, 100), 32);
+    }
+
+    #[test]
+    fn combination() {
+        let targets = [0, 99, 30, 30, 80];
+        let (total, instructions) = min_clicks_to(&targets, Dial::default());
+        assert_eq!(total, 50 + 1 + 31 + 50);
+        let turn = |dir, clicks| Instruction::Turn { dir, clicks };
+        assert_eq!(
+            instructions,
+            vec![
+                turn(Rotation::Right, 50),
+                turn(Rotation::Left, 1),
+                turn(Rotation::Right, 31),
+                turn(Rotation::Right, 50),
+            ]
+        );
+        assert_eq!(position_after(&instructions, instructions.len()), 80);
+        assert_eq!(part_a(&instructions), 1);
+
+        let (total, instructions) = min_clicks_to(&[7, 2], Dial { size: 10, start: 0 });
+        assert_eq!(total, 3 + 5);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(min_clicks_to(&[], Dial::default()), (0, Vec::new()));
+    }
+
+    #[test]
+    fn fractional_clicks() {
+        let input = "L12.5\nR192.5\nL0.1\nR0.1\nRESET\nL179.98\nR720";
+        let rotations = parse_fractional_input(input).unwrap();
+        // Only the rotations ending at 167.5 + 192.5 and 359.9 + 0.1 land on 0
+        let dial = Dial::<f64>::default();
+        assert_eq!(part_a_fractional(&rotations, dial, FRACTIONAL_EPSILON), 2);
+        // 0.02 away from 0 is close enough, while 359.9 is still 0.1 away
+        assert_eq!(part_a_fractional(&rotations, dial, 0.05), 4);
+
+        // The example scaled to the 360-unit dial lands on 0 as often
+        let scaled: Vec<_> = parse_input(EXAMPLE_INPUT, None)
+            .unwrap()
+            .into_iter()
+            .map(|instruction| match instruction {
+                Instruction::Turn { dir, clicks } => Instruction::Turn {
+                    dir,
+                    clicks: clicks as f64 * 3.6,
+                },
+                Instruction::Reset => Instruction::Reset,
+            })
+            .collect();
+        assert_eq!(part_a_fractional(&scaled, dial, FRACTIONAL_EPSILON), 3);
+
+        assert!(parse_fractional_input("L-5").is_err());
+        assert!(parse_fractional_input("Rinf").is_err());
+        assert!(parse_fractional_input("RNaN").is_err());
+        assert!(parse_input("L12.5", None).is_err());
+    }
+
+    #[test]
+    fn travel() {
+        let stats = travel_stats(&parse_input(EXAMPLE_INPUT, None).unwrap());
+        assert_eq!(
+            stats,
+            TravelStats {
+                total_clicks: 462,
+                max_excursion: 50,
+                final_pos: 32,
+            }
+        );
+
+        let stats = travel_stats(&parse_input("R10\nL25", None).unwrap());
+        assert_eq!(
+            stats,
+            TravelStats {
+                total_clicks: 35,
+                max_excursion: 15,
+                final_pos: 35,
+            }
+        );
     }
 }
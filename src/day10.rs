@@ -1,6 +1,11 @@
 //! # Day 10: Factory
 //! Input lists machines, one per line, each with an indicator target in brackets, a set of button
-//! wiring diagrams in parentheses, and per-light joltage requirements in braces.
+//! wiring diagrams in parentheses, and per-light joltage requirements in braces. A machine may
+//! continue onto following lines as long as they're indented. Joltage requirements may instead be
+//! given as a second bracket block with space-separated values like `[3 5 4 7]`. Blank lines and
+//! comment lines starting with `#` are ignored. Requirements may also be fractions like
+//! `{3/2,5,7/4}`, in which case every requirement of the machine is scaled by the least common
+//! denominator to get whole joltages.
 //!
 //! ## Part A
 //! Indicator lights start off. Pushing a button toggles the listed lights; pushes stack, so pushing
@@ -11,19 +16,60 @@
 //! Switch the buttons to increase joltage counters instead: each machine lists required counter
 //! values in braces and buttons add 1 to the listed counters. Starting from all-zero counters,
 //! find the minimum presses to reach each machine's exact joltage requirements and sum the presses.
+//! A light index in a button may carry an optional weight like `(0:3,2)`, in which case that button
+//! adds 3 instead of 1 to counter 0 but still only toggles the light once in part A.
+//!
+//! A `link` line like `link (1.0,2.1:2)` describes a button shared between machines, listing the
+//! one-based machine number and light index of every counter it raises. Machines connected by
+//! shared buttons are solved together as one system of counters.
 use anyhow::{Context, Result, bail};
 use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+pub const TITLE: &str = "Factory";
+
+/// Most buttons a GF(2) equation can hold, one bit per button.
+#[cfg(test)]
+const MAX_GF2_BUTTONS: usize = u64::BITS as usize;
+/// Most free buttons whose combinations `min_presses_gf2` is willing to try.
+#[cfg(test)]
+const MAX_GF2_FREE_BUTTONS: usize = 20;
+/// Keyword that starts a definition of buttons shared between machines.
+const LINK_KEYWORD: &str = "link ";
+
+/// Button shared between machines, as the zero-based machine index, light index and weight of every
+/// counter it raises.
+type SharedButton = Vec<(usize, usize, usize)>;
 
 #[derive(Debug)]
-struct Machine {
+pub struct Machine {
     target: u16,
     button_masks: Vec<u16>,
+    button_weights: Vec<Vec<usize>>,
     requirements: Vec<usize>,
     lights: usize,
 }
 
-/// Parse a machine line like `[.#.] (0,2) (0,1) {3,5,7}` into target mask, button masks, and
-/// joltage requirements.
+/// Parse a joltage requirement that's either a whole number or a fraction like `7/4`.
+fn parse_joltage(value: &str) -> Result<Fraction> {
+    let (num, den) = value.split_once('/').unwrap_or((value, "1"));
+    let num: u64 = num
+        .parse()
+        .with_context(|| format!("Invalid joltage value '{value}'"))?;
+    let den: u64 = den
+        .parse()
+        .with_context(|| format!("Invalid joltage value '{value}'"))?;
+    if den == 0 {
+        bail!("Joltage value '{value}' has a zero denominator");
+    }
+    Ok(Fraction::new(num.into(), den.into()))
+}
+
+/// Parse a machine line like `[.#.] (0,2) (0:2,1) {3,5,7}` or `[.#.] (0,2) (0:2,1) [3 5 7]` into
+/// target mask, button masks, per-light button weights, and joltage requirements. Fractional
+/// requirements are scaled by their least common denominator.
 fn parse_machine(line: &str) -> Result<Machine> {
     let line = line.trim();
     let mut chars = line.chars();
@@ -49,24 +95,56 @@ fn parse_machine(line: &str) -> Result<Machine> {
     }
 
     let rest = line[end_indicator + 1..].trim();
-    let brace_start = rest
-        .rfind('{')
+    let (open, close) = if rest.ends_with(']') {
+        ('[', ']')
+    } else {
+        ('{', '}')
+    };
+    let block_start = rest
+        .rfind(open)
         .context("Missing joltage requirement block")?;
-    let buttons_part = rest[..brace_start].trim();
-    let jolts_part = rest[brace_start..].trim();
-    if !jolts_part.ends_with('}') {
-        bail!("Missing closing '}}' for joltage requirements");
+    let buttons_part = rest[..block_start].trim();
+    let jolts_part = rest[block_start..].trim();
+    if !jolts_part.ends_with(close) {
+        bail!("Missing closing '{close}' for joltage requirements");
     }
     let jolts_str = &jolts_part[1..jolts_part.len() - 1];
-    let jolts: Vec<usize> = jolts_str
-        .split(',')
-        .map(|value| value.parse().context("Invalid joltage value"))
+    let values: Vec<&str> = if open == '[' {
+        jolts_str.split(' ').collect()
+    } else {
+        jolts_str.split(',').collect()
+    };
+    let fractions: Vec<Fraction> = values
+        .into_iter()
+        .map(parse_joltage)
         .collect::<Result<_>>()?;
-    if jolts.len() != lights {
-        bail!("Expected {} joltage entries, found {}", lights, jolts.len());
+    if fractions.len() != lights {
+        bail!(
+            "Expected {} joltage entries, found {}",
+            lights,
+            fractions.len()
+        );
     }
+    let denom = fractions
+        .iter()
+        .try_fold(1i128, |acc, fraction| {
+            acc.checked_lcm(&fraction.den)
+                .filter(|&lcm| lcm <= usize::MAX as i128)
+        })
+        .context("Common denominator of joltage requirements is too large")?;
+    let jolts: Vec<usize> = fractions
+        .iter()
+        .map(|fraction| {
+            fraction
+                .num
+                .checked_mul(denom / fraction.den)
+                .and_then(|jolts| usize::try_from(jolts).ok())
+                .context("Scaled joltage requirement is too large")
+        })
+        .collect::<Result<_>>()?;
 
     let mut button_masks = Vec::new();
+    let mut button_weights = Vec::new();
     let mut idx = 0;
     while idx < buttons_part.len() {
         while idx < buttons_part.len() && buttons_part.as_bytes()[idx].is_ascii_whitespace() {
@@ -85,11 +163,24 @@ fn parse_machine(line: &str) -> Result<Machine> {
             + after_open;
         let button_def = &buttons_part[after_open..close];
         let mut mask: u16 = 0;
+        let mut weights = vec![0; lights];
         if !button_def.is_empty() {
             for entry in button_def.split(',') {
-                let light_idx: usize = entry
+                let (light_str, weight) = match entry.split_once(':') {
+                    Some((light_str, weight_str)) => (
+                        light_str,
+                        weight_str
+                            .parse()
+                            .with_context(|| format!("Invalid button weight '{weight_str}'"))?,
+                    ),
+                    None => (entry, 1),
+                };
+                if weight == 0 {
+                    bail!("Button weight for light '{light_str}' must be positive");
+                }
+                let light_idx: usize = light_str
                     .parse()
-                    .with_context(|| format!("Invalid light index '{entry}'"))?;
+                    .with_context(|| format!("Invalid light index '{light_str}'"))?;
                 if light_idx >= lights {
                     bail!(
                         "Light index {} out of bounds for {lights}-light machine",
@@ -97,9 +188,11 @@ fn parse_machine(line: &str) -> Result<Machine> {
                     );
                 }
                 mask ^= 1 << light_idx;
+                weights[light_idx] += weight;
             }
         }
         button_masks.push(mask);
+        button_weights.push(weights);
         idx = close + 1;
     }
 
@@ -110,41 +203,235 @@ fn parse_machine(line: &str) -> Result<Machine> {
     Ok(Machine {
         target,
         button_masks,
+        button_weights,
         requirements: jolts,
         lights,
     })
 }
 
-/// Parse all machine definitions from the input.
-fn parse_input(input: &str) -> Result<Vec<Machine>> {
-    input.trim().lines().map(parse_machine).collect()
+/// Split the input into definitions, joining indented continuation lines onto the definition they
+/// belong to. Blank lines and lines starting with `#` are skipped.
+fn definitions(input: &str) -> Vec<String> {
+    let mut definitions: Vec<String> = Vec::new();
+    for line in input.trim().lines() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        match definitions.last_mut() {
+            Some(definition) if line.starts_with(char::is_whitespace) => {
+                definition.push(' ');
+                definition.push_str(line.trim());
+            }
+            _ => definitions.push(line.to_string()),
+        }
+    }
+    definitions
+}
+
+/// Parse all machine definitions from the input, joining indented continuation lines onto the
+/// machine they belong to. Blank lines and lines starting with `#` are skipped.
+pub fn parse_input(input: &str) -> Result<Vec<Machine>> {
+    definitions(input)
+        .iter()
+        .map(|definition| {
+            if definition.starts_with(LINK_KEYWORD) {
+                bail!("Linked machines are only supported by the linked solver");
+            }
+            parse_machine(definition)
+        })
+        .collect()
 }
 
-/// Return the minimum number of button presses needed to reach the target pattern.
-fn part_a(machines: &[Machine]) -> Result<usize> {
-    machines.iter().try_fold(0, |acc, machine| {
-        let states = 1usize << machine.lights;
-        let mut dist: Vec<Option<usize>> = vec![None; states];
-        let mut queue = VecDeque::new();
-        dist[0] = Some(0);
-        queue.push_back(0usize);
-        while let Some(state) = queue.pop_front() {
-            if state as u16 == machine.target {
-                break;
+/// Parse a link definition like `link (1.0,2.1:2) (2.0,3.0)` where every parenthesized group is one
+/// shared button listing the one-based machine number, the zero-based light index and an optional
+/// weight for each counter it raises.
+fn parse_link(definition: &str, machines: &[Machine]) -> Result<Vec<SharedButton>> {
+    let buttons_part = definition[LINK_KEYWORD.len()..].trim();
+    let mut shared = Vec::new();
+    for button_def in buttons_part.split_whitespace() {
+        let button_def = button_def
+            .strip_prefix('(')
+            .and_then(|def| def.strip_suffix(')'))
+            .with_context(|| format!("Invalid shared button '{button_def}'"))?;
+        let mut button = Vec::new();
+        for entry in button_def.split(',') {
+            let (target, weight) = match entry.split_once(':') {
+                Some((target, weight_str)) => (
+                    target,
+                    weight_str
+                        .parse()
+                        .with_context(|| format!("Invalid button weight '{weight_str}'"))?,
+                ),
+                None => (entry, 1),
+            };
+            if weight == 0 {
+                bail!("Button weight for '{target}' must be positive");
             }
-            let next_dist = dist[state].unwrap() + 1;
-            for &mask in &machine.button_masks {
-                let next = state ^ mask as usize;
-                if dist[next].is_none() {
-                    dist[next] = Some(next_dist);
-                    queue.push_back(next);
-                }
+            let (machine_str, light_str) = target
+                .split_once('.')
+                .with_context(|| format!("Shared button entry '{target}' must be machine.light"))?;
+            let machine_no: usize = machine_str
+                .parse()
+                .with_context(|| format!("Invalid machine number '{machine_str}'"))?;
+            let machine = machine_no
+                .checked_sub(1)
+                .and_then(|idx| machines.get(idx))
+                .with_context(|| format!("Machine {machine_no} doesn't exist"))?;
+            let light_idx: usize = light_str
+                .parse()
+                .with_context(|| format!("Invalid light index '{light_str}'"))?;
+            if light_idx >= machine.lights {
+                bail!(
+                    "Light index {} out of bounds for {}-light machine {machine_no}",
+                    light_idx,
+                    machine.lights
+                );
             }
+            button.push((machine_no - 1, light_idx, weight));
         }
-        let presses = dist[machine.target as usize]
-            .with_context(|| "Target configuration unreachable with given buttons")?;
-        Ok(acc + presses)
-    })
+        shared.push(button);
+    }
+    if shared.is_empty() {
+        bail!("Link must list at least one shared button");
+    }
+    Ok(shared)
+}
+
+/// Parse machine definitions along with the `link` definitions of buttons shared between machines.
+/// A link may only refer to machines defined before it.
+fn parse_linked_input(input: &str) -> Result<(Vec<Machine>, Vec<SharedButton>)> {
+    let mut machines = Vec::new();
+    let mut shared = Vec::new();
+    for definition in definitions(input) {
+        if definition.starts_with(LINK_KEYWORD) {
+            shared.extend(parse_link(&definition, &machines)?);
+        } else {
+            machines.push(parse_machine(&definition)?);
+        }
+    }
+    Ok((machines, shared))
+}
+
+/// Return the minimum number of button presses needed to turn the machine's lights from all off to
+/// its target pattern. A target with every light off is already reached without pressing anything.
+fn min_presses_lights(machine: &Machine) -> Result<usize> {
+    Ok(min_buttons_lights(machine)?.len())
+}
+
+/// Return the indices of a smallest set of buttons that turns the machine's lights from all off to
+/// its target pattern, in ascending order. Pressing a button twice cancels out, so every button in
+/// a minimal solution is pressed once.
+fn min_buttons_lights(machine: &Machine) -> Result<Vec<usize>> {
+    if machine.target == 0 {
+        return Ok(Vec::new());
+    }
+
+    // The state each light state was first reached from and the button pressed to get there
+    let states = 1usize << machine.lights;
+    let mut parent: Vec<Option<(usize, usize)>> = vec![None; states];
+    let mut queue = VecDeque::new();
+    queue.push_back(0usize);
+    while let Some(state) = queue.pop_front() {
+        if state as u16 == machine.target {
+            break;
+        }
+        for (button, &mask) in machine.button_masks.iter().enumerate() {
+            let next = state ^ mask as usize;
+            if next != 0 && parent[next].is_none() {
+                parent[next] = Some((state, button));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut buttons = Vec::new();
+    let mut state = machine.target as usize;
+    while state != 0 {
+        let (prev, button) =
+            parent[state].context("Target configuration unreachable with given buttons")?;
+        buttons.push(button);
+        state = prev;
+    }
+    buttons.sort_unstable();
+    Ok(buttons)
+}
+
+/// Return the minimum number of button presses needed to reach the target pattern by solving the
+/// toggle system over GF(2), with one equation per light and one variable per button. Unlike the
+/// BFS over light states this scales with the number of free buttons instead of lights.
+#[cfg(test)]
+fn min_presses_gf2(machine: &Machine) -> Result<usize> {
+    let num_buttons = machine.button_masks.len();
+    if num_buttons > MAX_GF2_BUTTONS {
+        bail!("Can't solve machines with more than {MAX_GF2_BUTTONS} buttons over GF(2)");
+    }
+
+    // Each row holds the buttons toggling one light and whether that light has to end up on
+    let mut rows: Vec<(u64, bool)> = (0..machine.lights)
+        .map(|light| {
+            let buttons = machine
+                .button_masks
+                .iter()
+                .enumerate()
+                .filter(|&(_, &mask)| mask & (1 << light) != 0)
+                .fold(0, |acc, (col, _)| acc | 1 << col);
+            (buttons, machine.target & (1 << light) != 0)
+        })
+        .collect();
+
+    let mut pivots = Vec::new();
+    for col in 0..num_buttons {
+        let rank = pivots.len();
+        let Some(pivot) = (rank..rows.len()).find(|&row| rows[row].0 & (1 << col) != 0) else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+        let (pivot_bits, pivot_on) = rows[rank];
+        for (row, (bits, on)) in rows.iter_mut().enumerate() {
+            if row != rank && *bits & (1 << col) != 0 {
+                *bits ^= pivot_bits;
+                *on ^= pivot_on;
+            }
+        }
+        pivots.push(col);
+    }
+    if rows[pivots.len()..].iter().any(|&(_, on)| on) {
+        bail!("Target configuration unreachable with given buttons");
+    }
+
+    let free: Vec<usize> = (0..num_buttons)
+        .filter(|col| !pivots.contains(col))
+        .collect();
+    if free.len() > MAX_GF2_FREE_BUTTONS {
+        bail!("Too many free buttons ({}) to search", free.len());
+    }
+
+    (0..1u64 << free.len())
+        .map(|choice| {
+            let free_pressed = free
+                .iter()
+                .enumerate()
+                .filter(|&(bit, _)| choice & (1 << bit) != 0)
+                .fold(0u64, |acc, (_, &col)| acc | 1 << col);
+            // After reduction every pivot button only shares its row with free buttons, so it has
+            // to be pressed exactly when the free buttons leave its light in the wrong state
+            let pressed = rows
+                .iter()
+                .zip(&pivots)
+                .filter(|&(&(bits, on), _)| on != ((bits & free_pressed).count_ones() % 2 == 1))
+                .fold(free_pressed, |acc, (_, &col)| acc | 1 << col);
+            pressed.count_ones() as usize
+        })
+        .min()
+        .context("No button combinations to search")
+}
+
+/// Return the minimum number of button presses needed to reach the target pattern of every machine.
+pub fn part_a(machines: &[Machine]) -> Result<usize> {
+    machines
+        .iter()
+        .try_fold(0, |acc, machine| Ok(acc + min_presses_lights(machine)?))
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -225,9 +512,10 @@ impl std::ops::Div for Fraction {
     }
 }
 
-trait GcdExt {
+trait GcdExt: Sized {
     fn gcd(&self, other: &Self) -> Self;
     fn lcm(&self, other: &Self) -> Self;
+    fn checked_lcm(&self, other: &Self) -> Option<Self>;
 }
 
 impl GcdExt for i128 {
@@ -249,6 +537,14 @@ impl GcdExt for i128 {
             (self / self.gcd(other)) * other.abs()
         }
     }
+
+    fn checked_lcm(&self, other: &Self) -> Option<Self> {
+        if *self == 0 || *other == 0 {
+            Some(0)
+        } else {
+            (self / self.gcd(other)).checked_mul(other.abs())
+        }
+    }
 }
 
 /// Bring a matrix to reduced row echelon form while applying the same operations to the right-hand
@@ -406,42 +702,92 @@ fn search_free_values(
     }
 }
 
-/// Return the minimum presses to reach the exact joltage requirements for one machine.
-fn min_presses_counters(machine: &Machine) -> Result<usize> {
-    if machine.requirements.iter().all(|&req| req == 0) {
-        return Ok(0);
+/// Build the linear system where each column holds how much a button raises every counter and the
+/// right-hand side holds the joltage requirements.
+fn button_matrix(
+    button_weights: &[Vec<usize>],
+    requirements: &[usize],
+) -> (Vec<Vec<Fraction>>, Vec<Fraction>) {
+    let mut matrix = vec![vec![Fraction::from_int(0); button_weights.len()]; requirements.len()];
+    for (col, weights) in button_weights.iter().enumerate() {
+        for (row, &weight) in matrix.iter_mut().zip(weights) {
+            row[col] = Fraction::from_int(weight as i128);
+        }
     }
-
-    let button_caps: Vec<usize> = machine
-        .button_masks
+    let rhs = requirements
         .iter()
-        .map(|&mask| {
-            let mut cap = usize::MAX;
-            for (idx, &req) in machine.requirements.iter().enumerate() {
-                if mask & (1 << idx) != 0 {
-                    cap = cap.min(req);
-                }
-            }
-            if cap == usize::MAX { 0 } else { cap }
-        })
+        .map(|&req| Fraction::from_int(req as i128))
         .collect();
+    (matrix, rhs)
+}
 
-    let rows = machine.lights;
-    let cols = machine.button_masks.len();
-    let mut matrix = vec![vec![Fraction::from_int(0); cols]; rows];
-    for (col, &mask) in machine.button_masks.iter().enumerate() {
-        for (row_idx, row) in matrix.iter_mut().enumerate() {
-            if mask & (1 << row_idx) != 0 {
-                row[col] = Fraction::from_int(1);
-            }
+/// Count the buttons that are free variables after row reduction, i.e. the dimension of the null
+/// space of the button matrix. Every free variable multiplies the work of `search_free_values`.
+#[cfg(test)]
+fn free_variable_count(machine: &Machine) -> Result<usize> {
+    let (mut matrix, mut rhs) = button_matrix(&machine.button_weights, &machine.requirements);
+    let num_pivots = rref(&mut matrix, &mut rhs)?.iter().flatten().count();
+    Ok(machine.button_masks.len() - num_pivots)
+}
+
+/// Return the minimum presses to reach the exact joltage requirements for one machine, or `None`
+/// if it can't be done in at most `budget` presses.
+fn solve_within(machine: &Machine, budget: usize) -> Result<Option<usize>> {
+    solve_counters_within(&machine.button_weights, &machine.requirements, budget)
+}
+
+/// Return the minimum presses for buttons raising counters by `button_weights` to reach exactly
+/// `requirements`, or `None` if it can't be done in at most `budget` presses.
+fn solve_counters_within(
+    button_weights: &[Vec<usize>],
+    requirements: &[usize],
+    budget: usize,
+) -> Result<Option<usize>> {
+    if requirements.iter().all(|&req| req == 0) {
+        return Ok(Some(0));
+    }
+
+    for (idx, &req) in requirements.iter().enumerate() {
+        if req > 0 && button_weights.iter().all(|weights| weights[idx] == 0) {
+            bail!("Light {idx} requires joltage {req} but no button is wired to it");
         }
     }
-    let mut rhs: Vec<Fraction> = machine
-        .requirements
+
+    // Each press raises a light by at most its heaviest wired button, which bounds the presses
+    // needed from below without searching
+    let min_presses = requirements
         .iter()
-        .map(|&req| Fraction::from_int(req as i128))
+        .enumerate()
+        .map(|(idx, &req)| {
+            let max_weight = button_weights
+                .iter()
+                .filter_map(|weights| weights.get(idx).copied())
+                .max()
+                .unwrap_or(0);
+            req.checked_div(max_weight).map_or(0, |presses| {
+                presses + usize::from(!req.is_multiple_of(max_weight))
+            })
+        })
+        .max()
+        .unwrap_or(0);
+    if min_presses > budget {
+        return Ok(None);
+    }
+
+    let button_caps: Vec<usize> = button_weights
+        .iter()
+        .map(|weights| {
+            weights
+                .iter()
+                .zip(requirements)
+                .filter_map(|(&weight, &req)| req.checked_div(weight))
+                .min()
+                .unwrap_or(0)
+        })
         .collect();
 
+    let cols = button_weights.len();
+    let (mut matrix, mut rhs) = button_matrix(button_weights, requirements);
     let pivot_cols = rref(&mut matrix, &mut rhs)?;
     let mut pivot_mask = vec![false; cols];
     for pivot in pivot_cols.iter().flatten() {
@@ -450,14 +796,15 @@ fn min_presses_counters(machine: &Machine) -> Result<usize> {
 
     if pivot_mask.iter().all(|&p| !p) {
         // No constraints left; the only way to stay within bounds is to press no buttons.
-        return Ok(0);
+        return Ok(Some(0));
     }
 
     let free_cols: Vec<usize> = (0..cols).filter(|&col| !pivot_mask[col]).collect();
     let pivot_exprs = build_pivot_expressions(&matrix, &rhs, &pivot_cols, &free_cols);
     let free_caps: Vec<usize> = free_cols.iter().map(|&col| button_caps[col]).collect();
     let mut free_values = vec![0usize; free_caps.len()];
-    let mut best = None;
+    // Seeding the search with an unreachable cost prunes every branch above the budget
+    let mut best = budget.checked_add(1);
     search_free_values(
         0,
         &free_caps,
@@ -468,19 +815,204 @@ fn min_presses_counters(machine: &Machine) -> Result<usize> {
         &mut best,
     );
 
-    best.context("Joltage requirements unreachable")
+    Ok(best.filter(|&presses| presses <= budget))
+}
+
+/// Return the minimum presses to reach the exact joltage requirements for one machine.
+fn min_presses_counters(machine: &Machine) -> Result<usize> {
+    solve_within(machine, usize::MAX)?.context("Joltage requirements unreachable")
+}
+
+/// Find the minimum presses to reach the exact joltage requirements by trying every combination of
+/// pressing each button at most `max_each` times. Only meant to cross-check `min_presses_counters`
+/// on small machines.
+#[cfg(test)]
+fn brute_force_counters(machine: &Machine, max_each: usize) -> Option<usize> {
+    /// Try every press count for the buttons from `button` onwards on top of `counters`.
+    fn search(
+        machine: &Machine,
+        button: usize,
+        counters: Vec<usize>,
+        max_each: usize,
+    ) -> Option<usize> {
+        let Some(weights) = machine.button_weights.get(button) else {
+            return (counters == machine.requirements).then_some(0);
+        };
+        (0..=max_each)
+            .scan(counters, |counters, presses| {
+                let current = counters.clone();
+                counters
+                    .iter_mut()
+                    .zip(weights)
+                    .for_each(|(counter, weight)| *counter += weight);
+                Some((presses, current))
+            })
+            // Pressing more only raises the counters further
+            .take_while(|(_, counters)| {
+                counters
+                    .iter()
+                    .zip(&machine.requirements)
+                    .all(|(counter, req)| counter <= req)
+            })
+            .filter_map(|(presses, counters)| {
+                Some(presses + search(machine, button + 1, counters, max_each)?)
+            })
+            .min()
+    }
+
+    search(machine, 0, vec![0; machine.lights], max_each)
+}
+
+/// Find the minimum presses to reach the joltage requirements of every machine, in input order.
+/// Machines are solved in parallel, with each worker picking the next unsolved machine until none
+/// remain.
+fn solve_machines(machines: &[Machine]) -> Vec<Result<usize>> {
+    let num_workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let next_machine = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<usize>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..num_workers.min(machines.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut solved = Vec::new();
+                    loop {
+                        let idx = next_machine.fetch_add(1, Ordering::Relaxed);
+                        let Some(machine) = machines.get(idx) else {
+                            break solved;
+                        };
+                        solved.push((idx, min_presses_counters(machine)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Machine solver panicked"))
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|&(idx, _)| idx);
+    results.into_iter().map(|(_, presses)| presses).collect()
 }
 
 /// Return the minimum presses to satisfy all joltage requirements across machines.
-fn part_b(machines: &[Machine]) -> Result<usize> {
-    machines.iter().try_fold(0usize, |acc, machine| {
-        Ok(acc + min_presses_counters(machine)?)
-    })
+pub fn part_b(machines: &[Machine]) -> Result<usize> {
+    // Report the first infeasible machine in input order regardless of which worker found it
+    solve_machines(machines)
+        .into_iter()
+        .enumerate()
+        .try_fold(0usize, |acc, (idx, presses)| {
+            Ok(acc + presses.with_context(|| format!("Machine {} is infeasible", idx + 1))?)
+        })
+}
+
+/// Return the minimum presses to satisfy all joltage requirements when some buttons are shared
+/// between machines. Machines connected through shared buttons are stacked into one system so a
+/// press counts once for all of them, while independent machines are solved one by one.
+fn part_b_linked(machines: &[Machine], shared: &[SharedButton]) -> Result<usize> {
+    if shared.is_empty() {
+        return part_b(machines);
+    }
+
+    /// Find the lowest machine index of the group `idx` belongs to.
+    fn root(group: &mut [usize], mut idx: usize) -> usize {
+        while group[idx] != idx {
+            group[idx] = group[group[idx]];
+            idx = group[idx];
+        }
+        idx
+    }
+
+    // Group machines connected by a shared button, always keeping the lowest index as the root
+    let mut group: Vec<usize> = (0..machines.len()).collect();
+    for button in shared {
+        for window in button.windows(2) {
+            let (a, b) = (root(&mut group, window[0].0), root(&mut group, window[1].0));
+            group[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut total = 0usize;
+    for first in 0..machines.len() {
+        if root(&mut group, first) != first {
+            continue;
+        }
+        let members: Vec<usize> = (first..machines.len())
+            .filter(|&idx| root(&mut group, idx) == first)
+            .collect();
+        let links: Vec<&SharedButton> = shared
+            .iter()
+            .filter(|button| {
+                button
+                    .first()
+                    .is_some_and(|&(idx, ..)| members.contains(&idx))
+            })
+            .collect();
+        if links.is_empty() {
+            total += min_presses_counters(&machines[first])
+                .with_context(|| format!("Machine {} is infeasible", first + 1))?;
+            continue;
+        }
+
+        // Stack the counters of every member, padding each button with zeros for the others
+        let mut offsets = vec![0; machines.len()];
+        let mut requirements = Vec::new();
+        for &idx in &members {
+            offsets[idx] = requirements.len();
+            requirements.extend(&machines[idx].requirements);
+        }
+        let mut button_weights = Vec::new();
+        for &idx in &members {
+            for weights in &machines[idx].button_weights {
+                let mut stacked = vec![0; requirements.len()];
+                stacked[offsets[idx]..offsets[idx] + weights.len()].copy_from_slice(weights);
+                button_weights.push(stacked);
+            }
+        }
+        for button in links {
+            let mut stacked = vec![0; requirements.len()];
+            for &(idx, light, weight) in button {
+                stacked[offsets[idx] + light] += weight;
+            }
+            button_weights.push(stacked);
+        }
+
+        let machine_nos: Vec<String> = members.iter().map(|idx| (idx + 1).to_string()).collect();
+        total += solve_counters_within(&button_weights, &requirements, usize::MAX)?.with_context(
+            || format!("Linked machines {} are infeasible", machine_nos.join(", ")),
+        )?;
+    }
+    Ok(total)
+}
+
+/// Return the minimum presses summed over every feasible machine along with the zero-based indices
+/// of the infeasible machines.
+fn part_b_report(machines: &[Machine]) -> (usize, Vec<usize>) {
+    let mut total = 0;
+    let mut infeasible = Vec::new();
+    for (idx, presses) in solve_machines(machines).into_iter().enumerate() {
+        match presses {
+            Ok(presses) => total += presses,
+            Err(_) => infeasible.push(idx),
+        }
+    }
+    (total, infeasible)
 }
 
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let machines = parse_input(input)?;
-    Ok((part_a(&machines)?, Some(part_b(&machines)?)))
+    let (machines, shared) = parse_linked_input(input)?;
+    let presses = if shared.is_empty() {
+        // Solve every machine before failing so all infeasible machines are listed at once
+        let (presses, infeasible) = part_b_report(&machines);
+        if !infeasible.is_empty() {
+            let machine_nos: Vec<String> =
+                infeasible.iter().map(|idx| (idx + 1).to_string()).collect();
+            bail!("Machines {} are infeasible", machine_nos.join(", "));
+        }
+        presses
+    } else {
+        part_b_linked(&machines, &shared)?
+    };
+    Ok((part_a(&machines)?, Some(presses)))
 }
 
 #[cfg(test)]
@@ -505,9 +1037,270 @@ mod test {
         assert_eq!(part_a(&machines).unwrap(), 7);
     }
 
+    #[test]
+    fn light_buttons() {
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        let solutions: Vec<Vec<usize>> = machines
+            .iter()
+            .map(|machine| min_buttons_lights(machine).unwrap())
+            .collect();
+        assert_eq!(solutions.iter().map(Vec::len).sum::<usize>(), 7);
+        for (machine, buttons) in machines.iter().zip(&solutions) {
+            assert!(buttons.windows(2).all(|pair| pair[0] < pair[1]));
+            let lights = buttons
+                .iter()
+                .fold(0, |lights, &button| lights ^ machine.button_masks[button]);
+            assert_eq!(lights, machine.target);
+        }
+
+        let machine = parse_machine("[..] (0) (1) {0,0}").unwrap();
+        assert!(min_buttons_lights(&machine).unwrap().is_empty());
+        let machine = parse_machine("[.#] (0) {0,1}").unwrap();
+        assert!(min_buttons_lights(&machine).is_err());
+    }
+
     #[test]
     fn example_b() {
         let machines = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(part_b(&machines).unwrap(), 33);
     }
+
+    #[test]
+    fn lights_already_off() {
+        let machines = parse_input("[....] (0,1) (2) {1,1,1,0}").unwrap();
+        assert_eq!(min_presses_lights(&machines[0]).unwrap(), 0);
+        assert_eq!(part_a(&machines).unwrap(), 0);
+
+        let machines = parse_input("[.#..] (0,1) (2) {1,1,1,0}").unwrap();
+        assert!(part_a(&machines).is_err());
+    }
+
+    #[test]
+    fn gf2_matches_bfs() {
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        let total: usize = machines
+            .iter()
+            .map(|machine| min_presses_gf2(machine).unwrap())
+            .sum();
+        assert_eq!(total, 7);
+
+        for machine in &machines {
+            for target in 0..1u16 << machine.lights {
+                let machine = Machine {
+                    target,
+                    button_masks: machine.button_masks.clone(),
+                    button_weights: machine.button_weights.clone(),
+                    requirements: machine.requirements.clone(),
+                    lights: machine.lights,
+                };
+                let bfs = min_presses_lights(&machine).ok();
+                assert_eq!(min_presses_gf2(&machine).ok(), bfs, "target {target:b}");
+            }
+        }
+
+        let machines = parse_input("[.#..] (0,1) (2) {1,1,1,0}").unwrap();
+        assert!(min_presses_gf2(&machines[0]).is_err());
+
+        let input = std::fs::read_to_string("data/day10.txt").unwrap();
+        let machines = parse_input(&input).unwrap();
+        let total: usize = machines
+            .iter()
+            .map(|machine| min_presses_gf2(machine).unwrap())
+            .sum();
+        assert_eq!(total, part_a(&machines).unwrap());
+    }
+
+    #[test]
+    fn continuation_lines() {
+        let input = dedent!(
+            r#"
+                [.##.] (3) (1,3) (2)
+                    (2,3) (0,2) (0,1)
+                    {3,5,4,7}
+                [...#.] (0,2,3,4) (2,3) (0,4) (0,1,2) (1,2,3,4) {7,5,12,7,2}
+                [.###.#] (0,1,2,3,4) (0,3,4)
+                  (0,1,2,4,5) (1,2) {10,11,11,5,10,5}
+            "#
+        );
+        let machines = parse_input(input).unwrap();
+        assert_eq!(machines.len(), 3);
+        assert_eq!(part_a(&machines).unwrap(), 7);
+        assert_eq!(part_b(&machines).unwrap(), 33);
+    }
+
+    #[test]
+    fn fractional_requirements() {
+        let machines =
+            parse_input("[.##.] (3) (1,3) (2) (2,3) (0,2) (0,1) {3/2,5/2,2,7/2}").unwrap();
+        assert_eq!(machines[0].requirements, vec![3, 5, 4, 7]);
+        assert_eq!(part_b(&machines).unwrap(), 10);
+
+        let machines = parse_input("[.#] (0) (0,1) [3/4 1/3]").unwrap();
+        assert_eq!(machines[0].requirements, vec![9, 4]);
+        assert_eq!(part_b(&machines).unwrap(), 9);
+        let machines = parse_input("[.#] (0) (0,1) {2/4,1}").unwrap();
+        assert_eq!(machines[0].requirements, vec![1, 2]);
+
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(machines[0].requirements, vec![3, 5, 4, 7]);
+        assert!(parse_input("[.#] (0) (0,1) {1/0,1}").is_err());
+        assert!(parse_input("[.#] (0) (0,1) {-1/2,1}").is_err());
+
+        let err = parse_input("[.#] (0) (0,1) {1/18446744073709551557,1/18446744073709551556}")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Common denominator of joltage requirements is too large"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let input = dedent!(
+            r#"
+                # Toggles lights 1 and 2
+                [.##.] (3) (1,3) (2) (2,3) (0,2) (0,1)
+                    # Continuation lines still belong to the machine above
+                    {3,5,4,7}
+
+                [...#.] (0,2,3,4) (2,3) (0,4) (0,1,2) (1,2,3,4) {7,5,12,7,2}
+                  #[.#] (0) {1}
+                [.###.#] (0,1,2,3,4) (0,3,4) (0,1,2,4,5) (1,2) {10,11,11,5,10,5}
+            "#
+        );
+        let machines = parse_input(input).unwrap();
+        assert_eq!(machines.len(), 3);
+        assert_eq!(part_a(&machines).unwrap(), 7);
+        assert_eq!(part_b(&machines).unwrap(), 33);
+    }
+
+    #[test]
+    fn bracket_requirements() {
+        let input = EXAMPLE_INPUT
+            .lines()
+            .map(|line| {
+                let (machine, jolts) = line.split_once('{').unwrap();
+                format!(
+                    "{machine}[{}]",
+                    jolts.trim_end_matches('}').replace(',', " ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let machines = parse_input(&input).unwrap();
+        assert_eq!(part_a(&machines).unwrap(), 7);
+        assert_eq!(part_b(&machines).unwrap(), 33);
+
+        assert!(parse_machine("[.#] (0) (1) [1  2]").is_err());
+        assert!(parse_machine("[.#] (0) (1) [1,2]").is_err());
+        assert!(parse_machine("[.#] (0) (1) {1 2}").is_err());
+    }
+
+    #[test]
+    fn weighted_buttons() {
+        let machine = parse_machine("[..] (0:2) (1) {4,3}").unwrap();
+        assert_eq!(min_presses_counters(&machine).unwrap(), 5);
+
+        let machine = parse_machine("[#.] (0:3,1) (1) {6,5}").unwrap();
+        assert_eq!(machine.button_masks, vec![0b01 | 0b10, 0b10]);
+        assert_eq!(min_presses_counters(&machine).unwrap(), 5);
+
+        assert!(parse_machine("[#.] (0:0) {1,0}").is_err());
+    }
+
+    #[test]
+    fn press_budget() {
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        for machine in &machines {
+            let presses = min_presses_counters(machine).unwrap();
+            assert_eq!(solve_within(machine, 1000).unwrap(), Some(presses));
+            assert_eq!(solve_within(machine, presses).unwrap(), Some(presses));
+            assert_eq!(solve_within(machine, presses - 1).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn infeasible_report() {
+        let input = dedent!(
+            r#"
+                [.##.] (3) (1,3) (2) (2,3) (0,2) (0,1) {3,5,4,7}
+                [.#.] (0) (0,1) {1,2,3}
+                [...#.] (0,2,3,4) (2,3) (0,4) (0,1,2) (1,2,3,4) {7,5,12,7,2}
+                [.###.#] (0,1,2,3,4) (0,3,4) (0,1,2,4,5) (1,2) {10,11,11,5,10,5}
+                [..] (0:2) (1) {3,1}
+            "#
+        );
+        let machines = parse_input(input).unwrap();
+        assert_eq!(part_b_report(&machines), (33, vec![1, 4]));
+        assert!(part_b(&machines).is_err());
+        let err = main(input).unwrap_err();
+        assert_eq!(err.to_string(), "Machines 2, 5 are infeasible");
+
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part_b_report(&machines), (33, vec![]));
+    }
+
+    #[test]
+    fn free_variables() {
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        let counts: Vec<_> = machines
+            .iter()
+            .map(|machine| free_variable_count(machine).unwrap())
+            .collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn brute_force() {
+        let machines = parse_input(EXAMPLE_INPUT).unwrap();
+        for machine in &machines {
+            let max_each = machine.requirements.iter().copied().max().unwrap();
+            assert_eq!(
+                brute_force_counters(machine, max_each),
+                Some(min_presses_counters(machine).unwrap())
+            );
+        }
+
+        let machine = parse_machine("[..] (0:2) (1) {3,1}").unwrap();
+        assert_eq!(brute_force_counters(&machine, 3), None);
+    }
+
+    #[test]
+    fn orphaned_light() {
+        let machine = parse_machine("[.#.] (0) (0,1) {1,2,3}").unwrap();
+        let err = min_presses_counters(&machine).unwrap_err();
+        assert!(err.to_string().contains("Light 2"), "{err}");
+
+        let machines = parse_input("[.#.] (0) (1,2) {1,2,2}\n[.#.] (0) (0,1) {1,2,3}").unwrap();
+        let err = part_b(&machines).unwrap_err();
+        assert_eq!(err.to_string(), "Machine 2 is infeasible");
+    }
+
+    #[test]
+    fn linked_machines() {
+        let input = dedent!(
+            r#"
+                [.#] (0) (1) {2,3}
+                [.#] (0) (1) {3,1}
+                link (1.1,2.0)
+            "#
+        );
+        let (machines, shared) = parse_linked_input(input).unwrap();
+        assert_eq!(shared, vec![vec![(0, 1, 1), (1, 0, 1)]]);
+        assert_eq!(part_b(&machines).unwrap(), 9);
+        assert_eq!(part_b_linked(&machines, &shared).unwrap(), 6);
+        assert_eq!(part_b_linked(&machines, &[]).unwrap(), 9);
+
+        // Machines without links are still solved on their own
+        let example = EXAMPLE_INPUT.lines().next().unwrap();
+        let (machines, shared) = parse_linked_input(&format!("{input}\n{example}")).unwrap();
+        assert_eq!(machines.len(), 3);
+        assert_eq!(part_b_linked(&machines, &shared).unwrap(), 16);
+
+        let err = parse_linked_input("[.#] (0) (1) {2,3}\nlink (2.0)").unwrap_err();
+        assert_eq!(err.to_string(), "Machine 2 doesn't exist");
+        assert!(parse_linked_input("[.#] (0) (1) {2,3}\nlink (1.2)").is_err());
+        assert!(parse_linked_input("[.#] (0) (1) {2,3}\nlink (1.0:0)").is_err());
+        assert!(parse_input(input).is_err());
+    }
 }
@@ -10,6 +10,10 @@
 //! IDs are invalid if their digits are any sequence repeated two or more times; sum all invalid IDs
 //! in the ranges.
 use anyhow::{Context, Result, bail};
+#[cfg(test)]
+use std::collections::HashSet;
+
+pub const TITLE: &str = "Gift Shop";
 
 #[derive(Debug, Clone, Copy)]
 struct Range {
@@ -49,29 +53,95 @@ fn parse_input(input: &str) -> Result<Vec<Range>> {
         .collect()
 }
 
+/// Generate all numbers with exactly `num_digits` decimal digits that are formed by repeating a
+/// base sequence, retaining only repeat counts accepted by `filter_repeat` and bases accepted by
+/// `filter_base`. A number formed by several bases is kept if any of them is accepted.
+fn repeated_numbers_of_length_by_base<F: Fn(usize) -> bool, B: Fn(usize) -> bool>(
+    num_digits: usize,
+    filter_repeat: F,
+    filter_base: B,
+) -> Vec<usize> {
+    let mut numbers: Vec<usize> = (1..=num_digits / 2)
+        .filter(|&base_len| {
+            num_digits.is_multiple_of(base_len) && filter_repeat(num_digits / base_len)
+        })
+        .flat_map(|base_len| {
+            let pow_base = 10usize.pow(base_len as u32);
+            let factor = (10usize.pow(num_digits as u32) - 1) / (pow_base - 1);
+            (pow_base / 10..pow_base)
+                .filter(|&base| filter_base(base))
+                .map(move |base| base * factor)
+        })
+        .collect();
+
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers
+}
+
 /// Generate all numbers up to `max_value` whose decimal digits are formed by repeating a base
-/// sequence `repeat_count` times, retaining only repeat counts accepted by `filter_repeat`.
+/// sequence, retaining only repeat counts accepted by `filter_repeat`.
 fn repeated_numbers<F: Fn(usize) -> bool>(max_value: usize, filter_repeat: F) -> Vec<usize> {
+    repeated_numbers_by_base(max_value, filter_repeat, |_| true)
+}
+
+/// Generate all numbers up to `max_value` whose decimal digits are formed by repeating a base
+/// sequence, retaining only repeat counts accepted by `filter_repeat` and bases accepted by
+/// `filter_base`.
+fn repeated_numbers_by_base<F: Fn(usize) -> bool, B: Fn(usize) -> bool>(
+    max_value: usize,
+    filter_repeat: F,
+    filter_base: B,
+) -> Vec<usize> {
+    let max_digits = max_value.to_string().len();
+    // Shorter numbers are always smaller, so the lengths in order are already sorted
+    (1..=max_digits)
+        .flat_map(|num_digits| {
+            repeated_numbers_of_length_by_base(num_digits, &filter_repeat, &filter_base)
+        })
+        .take_while(|&candidate| candidate <= max_value)
+        .collect()
+}
+
+/// Check whether the decimal digits of `n` are a base sequence repeated a number of times accepted
+/// by `filter_repeat`.
+#[cfg(test)]
+fn is_repeated<F: Fn(usize) -> bool>(n: usize, filter_repeat: F) -> bool {
+    let digits = n.to_string();
+    (2..=digits.len())
+        .filter(|&num_repeats| {
+            digits.len().is_multiple_of(num_repeats) && filter_repeat(num_repeats)
+        })
+        .any(|num_repeats| {
+            let base = &digits[..digits.len() / num_repeats];
+            base.repeat(num_repeats) == digits
+        })
+}
+
+/// Check whether the decimal digits of `n` are a base sequence repeated exactly twice, which holds
+/// when it has an even number of digits and is a multiple of `10^half + 1`.
+fn is_double(n: usize) -> bool {
+    let num_digits = n.checked_ilog10().map_or(1, |log| log + 1);
+    num_digits.is_multiple_of(2) && n.is_multiple_of(10usize.pow(num_digits / 2) + 1)
+}
+
+/// Generate all even-length palindromes up to `max_value` by mirroring every possible first half.
+#[cfg(test)]
+fn palindromic_numbers(max_value: usize) -> Vec<usize> {
     let mut numbers = Vec::new();
     let max_digits = max_value.to_string().len();
 
-    for base_len in 1..=max_digits {
-        let pow_base = 10usize.pow(base_len as u32);
-        let base_start = pow_base / 10;
-        let base_end = pow_base - 1;
-        for num_repeats in 2..=max_digits / base_len {
-            if !filter_repeat(num_repeats) {
-                continue;
-            }
-            let pow_total = 10usize.pow((base_len * num_repeats) as u32);
-            let factor = (pow_total - 1) / (pow_base - 1);
-            for base in base_start..=base_end {
-                let candidate = base * factor;
-                if candidate > max_value {
-                    break;
-                }
-                numbers.push(candidate);
+    for half_len in 1..=max_digits / 2 {
+        let pow_half = 10usize.pow(half_len as u32);
+        for half in pow_half / 10..pow_half {
+            let mirrored =
+                std::iter::successors(Some(half), |&rest| (rest >= 10).then_some(rest / 10))
+                    .fold(0, |acc, rest| acc * 10 + rest % 10);
+            let candidate = half * pow_half + mirrored;
+            if candidate > max_value {
+                break;
             }
+            numbers.push(candidate);
         }
     }
 
@@ -80,45 +150,145 @@ fn repeated_numbers<F: Fn(usize) -> bool>(max_value: usize, filter_repeat: F) ->
     numbers
 }
 
-/// Sum every repeated-half number that falls inside any of the provided inclusive ranges.
-fn part_a(ranges: &[Range]) -> usize {
-    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
-    if max_value == 0 {
-        return 0;
-    }
+/// Return the part of a sorted list that falls inside the inclusive range.
+fn slice_in_range<'a>(sorted: &'a [usize], range: &Range) -> &'a [usize] {
+    let start_idx = sorted.partition_point(|&value| value < range.start);
+    let end_idx = sorted.partition_point(|&value| value <= range.end);
+    &sorted[start_idx..end_idx]
+}
+
+/// Sum the values of a sorted list that fall inside any of the provided inclusive ranges.
+#[cfg(test)]
+fn sum_in_ranges(sorted: &[usize], ranges: &[Range]) -> usize {
+    ranges
+        .iter()
+        .map(|range| slice_in_range(sorted, range).iter().sum::<usize>())
+        .sum()
+}
 
-    let doubles = repeated_numbers(max_value, |num_repeats| num_repeats == 2);
+/// Sum the values of a sorted list that fall inside any of the provided inclusive ranges, failing
+/// instead of overflowing.
+#[cfg(test)]
+fn checked_sum_in_ranges(sorted: &[usize], ranges: &[Range]) -> Result<usize> {
     ranges
         .iter()
-        .map(|range| {
-            let start_idx = doubles.partition_point(|&value| value < range.start);
-            let end_idx = doubles.partition_point(|&value| value <= range.end);
-            doubles[start_idx..end_idx].iter().sum::<usize>()
+        .flat_map(|range| slice_in_range(sorted, range))
+        .try_fold(0usize, |acc, &value| {
+            acc.checked_add(value)
+                .with_context(|| format!("Sum of invalid IDs overflows at {value}"))
         })
+}
+
+/// Count the values of a sorted list that fall inside any of the provided inclusive ranges.
+#[cfg(test)]
+fn count_in_ranges(sorted: &[usize], ranges: &[Range]) -> usize {
+    ranges
+        .iter()
+        .map(|range| slice_in_range(sorted, range).len())
         .sum()
 }
 
-/// Sum every repeated-sequence number (two or more repeats) that falls inside any of the ranges.
-fn part_b(ranges: &[Range]) -> usize {
+/// Count every repeated-sequence number inside any of the ranges, retaining only repeat counts
+/// accepted by `filter_repeat`. Unlike the sums this can't overflow.
+#[cfg(test)]
+fn count_invalid<F: Fn(usize) -> bool>(ranges: &[Range], filter_repeat: F) -> usize {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    count_in_ranges(&repeated_numbers(max_value, filter_repeat), ranges)
+}
+
+/// Sum every repeated-sequence number inside any of the ranges, retaining only repeat counts
+/// accepted by `filter_repeat`. Fails if the sum doesn't fit in a `usize`.
+#[cfg(test)]
+fn checked_sum_invalid<F: Fn(usize) -> bool>(ranges: &[Range], filter_repeat: F) -> Result<usize> {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    checked_sum_in_ranges(&repeated_numbers(max_value, filter_repeat), ranges)
+}
+
+/// Return the number and sum of repeated-sequence numbers inside each range, retaining only repeat
+/// counts accepted by `filter_repeat`.
+#[cfg(test)]
+fn breakdown<F: Fn(usize) -> bool>(
+    ranges: &[Range],
+    filter_repeat: F,
+) -> Vec<(Range, usize, usize)> {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    let repeated = repeated_numbers(max_value, filter_repeat);
+    ranges
+        .iter()
+        .map(|&range| {
+            let invalid = slice_in_range(&repeated, &range);
+            (range, invalid.len(), invalid.iter().sum())
+        })
+        .collect()
+}
+
+/// Sum every repeated-sequence number inside any of the ranges, retaining only repeat counts
+/// accepted by `filter_repeat`. Repeating one of the `allowed_bases` doesn't make an ID invalid,
+/// though an ID that's also a repeat of another base still counts.
+#[cfg(test)]
+fn sum_invalid_excluding_bases<F: Fn(usize) -> bool>(
+    ranges: &[Range],
+    filter_repeat: F,
+    allowed_bases: &[&str],
+) -> usize {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    let repeated = repeated_numbers_by_base(max_value, filter_repeat, |base| {
+        !allowed_bases.contains(&base.to_string().as_str())
+    });
+    sum_in_ranges(&repeated, ranges)
+}
+
+/// Sum every even-length palindrome that falls inside any of the provided inclusive ranges.
+#[cfg(test)]
+fn sum_palindromic(ranges: &[Range]) -> usize {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    if max_value == 0 {
+        return 0;
+    }
+
+    let palindromes = palindromic_numbers(max_value);
+    sum_in_ranges(&palindromes, ranges)
+}
+
+/// Sum every repeated-half number that falls inside any of the provided inclusive ranges, except
+/// for the exempt IDs.
+#[cfg(test)]
+fn part_a_excluding(ranges: &[Range], exempt: &HashSet<usize>) -> usize {
     let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
     if max_value == 0 {
         return 0;
     }
 
+    let doubles: Vec<_> = repeated_numbers(max_value, |num_repeats| num_repeats == 2)
+        .into_iter()
+        .filter(|id| !exempt.contains(id))
+        .collect();
+    sum_in_ranges(&doubles, ranges)
+}
+
+/// Sum the repeated-half numbers and the repeated-sequence numbers inside the ranges, generating
+/// the repeated-sequence numbers only once since they include every repeated-half number.
+fn solve(ranges: &[Range]) -> (usize, usize) {
+    let max_value = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    if max_value == 0 {
+        return (0, 0);
+    }
+
     let repeated = repeated_numbers(max_value, |num_repeats| num_repeats >= 2);
-    ranges
-        .iter()
-        .map(|range| {
-            let start_idx = repeated.partition_point(|&value| value < range.start);
-            let end_idx = repeated.partition_point(|&value| value <= range.end);
-            repeated[start_idx..end_idx].iter().sum::<usize>()
-        })
-        .sum()
+    let mut doubles = 0;
+    let mut all = 0;
+    for range in ranges {
+        let invalid = slice_in_range(&repeated, range);
+        doubles += invalid.iter().filter(|&&n| is_double(n)).sum::<usize>();
+        all += invalid.iter().sum::<usize>();
+    }
+    (doubles, all)
 }
 
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
     let ranges = parse_input(input)?;
-    Ok((part_a(&ranges), Some(part_b(&ranges))))
+    let (a, b) = solve(&ranges);
+    Ok((a, Some(b)))
 }
 
 #[cfg(test)]
@@ -139,11 +309,153 @@ mod test {
 
     #[test]
     fn example_a() {
-        assert_eq!(part_a(&parse_input(EXAMPLE_INPUT).unwrap()), 1_227_775_554);
+        assert_eq!(solve(&parse_input(EXAMPLE_INPUT).unwrap()).0, 1_227_775_554);
     }
 
     #[test]
     fn example_b() {
-        assert_eq!(part_b(&parse_input(EXAMPLE_INPUT).unwrap()), 4_174_379_265);
+        assert_eq!(solve(&parse_input(EXAMPLE_INPUT).unwrap()).1, 4_174_379_265);
+    }
+
+    #[test]
+    fn combined() {
+        let ranges = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(
+            solve(&ranges),
+            (
+                checked_sum_invalid(&ranges, |num_repeats| num_repeats == 2).unwrap(),
+                checked_sum_invalid(&ranges, |num_repeats| num_repeats >= 2).unwrap()
+            )
+        );
+        assert!(
+            (0..100_000).all(|n| is_double(n) == is_repeated(n, |num_repeats| num_repeats == 2))
+        );
+    }
+
+    #[test]
+    fn breakdown_per_range() {
+        let ranges = parse_input(EXAMPLE_INPUT).unwrap();
+        let (sum_a, _) = solve(&ranges);
+        let per_range = breakdown(&ranges, |num_repeats| num_repeats == 2);
+        let counts: Vec<_> = per_range.iter().map(|&(_, count, _)| count).collect();
+        assert_eq!(counts, vec![2, 1, 1, 1, 1, 0, 1, 1, 0, 0, 0]);
+        assert_eq!(
+            per_range.iter().map(|&(_, _, sum)| sum).sum::<usize>(),
+            sum_a
+        );
+    }
+
+    #[test]
+    fn invalid_counts() {
+        let ranges = parse_input(EXAMPLE_INPUT).unwrap();
+        let (sum_a, sum_b) = solve(&ranges);
+        let doubles = |num_repeats| num_repeats == 2;
+        let repeated = |num_repeats| num_repeats >= 2;
+        assert_eq!(count_invalid(&ranges, doubles), 8);
+        assert_eq!(count_invalid(&ranges, repeated), 13);
+        assert_eq!(checked_sum_invalid(&ranges, doubles).unwrap(), sum_a);
+        assert_eq!(checked_sum_invalid(&ranges, repeated).unwrap(), sum_b);
+
+        let huge = [usize::MAX / 2 + 1, usize::MAX];
+        let everything = [Range {
+            start: 0,
+            end: usize::MAX,
+        }];
+        assert_eq!(count_in_ranges(&huge, &everything), 2);
+        let err = checked_sum_in_ranges(&huge, &everything).unwrap_err();
+        assert!(err.to_string().contains("overflows"), "{err}");
+    }
+
+    #[test]
+    fn repeated_predicate() {
+        const MAX_VALUE: usize = 100_000;
+
+        for filter_repeat in [
+            |num_repeats| num_repeats == 2,
+            |num_repeats| num_repeats >= 2,
+        ] {
+            let generated = repeated_numbers(MAX_VALUE, filter_repeat);
+            let checked: Vec<_> = (1..=MAX_VALUE)
+                .filter(|&n| is_repeated(n, filter_repeat))
+                .collect();
+            assert_eq!(generated, checked);
+        }
+        assert!(is_repeated(121_212, |num_repeats| num_repeats == 3));
+        assert!(!is_repeated(121_212, |num_repeats| num_repeats == 2));
+    }
+
+    #[test]
+    fn fixed_length() {
+        assert_eq!(
+            repeated_numbers_of_length_by_base(4, |num_repeats| num_repeats == 4, |_| true),
+            vec![1111, 2222, 3333, 4444, 5555, 6666, 7777, 8888, 9999]
+        );
+        let doubles =
+            repeated_numbers_of_length_by_base(4, |num_repeats| num_repeats == 2, |_| true);
+        assert_eq!(doubles.len(), 90);
+        assert_eq!(doubles[..3], [1010, 1111, 1212]);
+        assert_eq!(doubles[88..], [9898, 9999]);
+        assert_eq!(
+            repeated_numbers_of_length_by_base(4, |num_repeats| num_repeats >= 2, |_| true),
+            doubles
+        );
+        assert!(repeated_numbers_of_length_by_base(1, |_| true, |_| true).is_empty());
+    }
+
+    #[test]
+    fn exempt_ids() {
+        let ranges = parse_input(EXAMPLE_INPUT).unwrap();
+        let (sum_a, _) = solve(&ranges);
+        assert_eq!(part_a_excluding(&ranges, &HashSet::new()), sum_a);
+        assert_eq!(
+            part_a_excluding(&ranges, &HashSet::from([1010])),
+            sum_a - 1010
+        );
+        assert_eq!(
+            part_a_excluding(&ranges, &HashSet::from([4444, 1011])),
+            sum_a
+        );
+    }
+
+    #[test]
+    fn palindromic() {
+        let ranges = parse_input("10-100,990-1010,123320-123322").unwrap();
+        assert_eq!(sum_palindromic(&ranges), 495 + 1001 + 123_321);
+    }
+
+    #[test]
+    fn allowed_bases() {
+        let ranges = parse_input(EXAMPLE_INPUT).unwrap();
+        let (sum_a, sum_b) = solve(&ranges);
+        let doubles = |num_repeats| num_repeats == 2;
+        let repeated = |num_repeats| num_repeats >= 2;
+        assert_eq!(sum_invalid_excluding_bases(&ranges, doubles, &[]), sum_a);
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, doubles, &["1", "2"]),
+            sum_a - 11 - 22
+        );
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, repeated, &["1"]),
+            sum_b - 11 - 111
+        );
+
+        // 1111 is both `11` repeated twice and `1` repeated four times
+        let ranges = parse_input("1100-1200,111100-111200").unwrap();
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, doubles, &[]),
+            1111 + 111_111
+        );
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, doubles, &["11"]),
+            111_111
+        );
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, repeated, &["11"]),
+            1111 + 111_111
+        );
+        assert_eq!(
+            sum_invalid_excluding_bases(&ranges, repeated, &["1", "11", "111"]),
+            0
+        );
     }
 }
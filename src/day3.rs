@@ -10,20 +10,38 @@
 //! For each bank, select exactly twelve batteries in order to form the largest possible
 //! twelve-digit number; sum these numbers across all banks.
 use anyhow::{Result, bail};
+#[cfg(test)]
+use std::cmp::Reverse;
+use std::num::NonZeroUsize;
+use std::thread;
+
+pub const TITLE: &str = "Lobby";
 
+const DECIMAL_BASE: u32 = 10;
+/// Smallest base that has a digit other than zero.
+const MIN_RADIX: u32 = 2;
+/// Largest base whose digits can be rendered as a single alphanumeric character.
+const MAX_RADIX: u32 = 36;
 const NUM_PICKS_A: usize = 2;
 const NUM_PICKS_B: usize = 12;
+/// Number of banks from which it pays off to spread the work across threads.
+const PARALLEL_THRESHOLD: usize = 10_000;
 
-/// Parse banks of battery ratings (digits 1-9).
-fn parse_input(input: &str) -> Result<Vec<Vec<usize>>> {
-    input
+/// Parse banks of battery ratings as non-zero digits in the given base (1-9 for base 10), which
+/// must be between 2 and 36. In `strict` mode every bank must have as many batteries as the first
+/// one.
+fn parse_input(input: &str, base: u32, strict: bool) -> Result<Vec<Vec<usize>>> {
+    if !(MIN_RADIX..=MAX_RADIX).contains(&base) {
+        bail!("Base {base} must be between {MIN_RADIX} and {MAX_RADIX}");
+    }
+    let banks = input
         .trim()
         .lines()
         .enumerate()
         .map(|(idx, line)| {
             let line_no = idx + 1;
             line.chars()
-                .map(|ch| match ch.to_digit(10) {
+                .map(|ch| match ch.to_digit(base) {
                     Some(0) | None => {
                         bail!("Invalid battery rating `{}` on line {}", ch, line_no)
                     }
@@ -31,11 +49,24 @@ fn parse_input(input: &str) -> Result<Vec<Vec<usize>>> {
                 })
                 .collect::<Result<Vec<_>>>()
         })
-        .collect()
+        .collect::<Result<Vec<_>>>()?;
+
+    if strict
+        && let Some(first) = banks.first()
+        && let Some(idx) = banks.iter().position(|bank| bank.len() != first.len())
+    {
+        bail!(
+            "Bank on line {} has {} batteries but line 1 has {}",
+            idx + 1,
+            banks[idx].len(),
+            first.len()
+        );
+    }
+    Ok(banks)
 }
 
-/// Build the largest possible `num_picks`-digit number by keeping digits in order.
-fn max_bank_joltage(batteries: &[usize], num_picks: usize) -> Result<usize> {
+/// Pick the indices of the `num_picks` batteries that form the largest number when kept in order.
+fn max_bank_selection(batteries: &[usize], num_picks: usize) -> Result<Vec<usize>> {
     if batteries.len() < num_picks {
         bail!(
             "Bank needs at least {} batteries but only has {}",
@@ -48,39 +79,173 @@ fn max_bank_joltage(batteries: &[usize], num_picks: usize) -> Result<usize> {
     let mut remaining = batteries.len();
 
     // Remove smaller leading digits while enough remain to reach length.
-    for &digit in batteries {
-        while !stack.is_empty()
-            && stack.len() + remaining > num_picks
-            && stack.last() < Some(&digit)
+    for (idx, &digit) in batteries.iter().enumerate() {
+        while stack.len() + remaining > num_picks
+            && stack
+                .last()
+                .is_some_and(|&last: &usize| batteries[last] < digit)
         {
             stack.pop();
         }
         if stack.len() < num_picks {
-            stack.push(digit);
+            stack.push(idx);
         }
         remaining -= 1;
     }
 
-    Ok(stack.into_iter().fold(0, |acc, digit| acc * 10 + digit))
+    Ok(stack)
 }
 
-/// Sum the highest two-digit values obtainable from each bank.
-fn part_a(banks: &[Vec<usize>]) -> Result<usize> {
+/// Build the largest possible `num_picks`-digit number in the given base by keeping digits in
+/// order.
+fn max_bank_joltage(batteries: &[usize], num_picks: usize, base: u32) -> Result<usize> {
+    Ok(max_bank_selection(batteries, num_picks)?
+        .into_iter()
+        .fold(0, |acc, idx| acc * base as usize + batteries[idx]))
+}
+
+/// Return up to `m` of the largest distinct `num_picks`-digit numbers in the given base that can be
+/// built by keeping digits in order, largest first.
+#[cfg(test)]
+fn top_bank_joltages(
+    batteries: &[usize],
+    num_picks: usize,
+    base: u32,
+    m: usize,
+) -> Result<Vec<usize>> {
+    /// Pick the `remaining` digits after `value` from `batteries[start..]`, trying larger digits
+    /// first so numbers are found in descending order, until `top` holds `m` numbers.
+    fn search(
+        batteries: &[usize],
+        start: usize,
+        remaining: usize,
+        value: usize,
+        base: u32,
+        m: usize,
+        top: &mut Vec<usize>,
+    ) {
+        if top.len() == m {
+            return;
+        }
+        if remaining == 0 {
+            top.push(value);
+            return;
+        }
+
+        // Enough batteries must be left after the pick to complete the number. The first
+        // occurrence of a digit leaves the most choices, so later ones only repeat its numbers.
+        let window = &batteries[start..=batteries.len() - remaining];
+        let mut firsts: Vec<(usize, usize)> = window
+            .iter()
+            .enumerate()
+            .map(|(offset, &digit)| (digit, start + offset))
+            .collect();
+        firsts.sort_unstable_by_key(|&(digit, idx)| (Reverse(digit), idx));
+        firsts.dedup_by_key(|&mut (digit, _)| digit);
+        for (digit, idx) in firsts {
+            let value = value * base as usize + digit;
+            search(batteries, idx + 1, remaining - 1, value, base, m, top);
+        }
+    }
+
+    if batteries.len() < num_picks {
+        bail!(
+            "Bank needs at least {} batteries but only has {}",
+            num_picks,
+            batteries.len()
+        );
+    }
+
+    let mut top = Vec::with_capacity(m);
+    search(batteries, 0, num_picks, 0, base, m, &mut top);
+    Ok(top)
+}
+
+/// Return the largest possible sum of `num_picks` batteries. Unlike `max_bank_joltage` the order of
+/// the batteries doesn't matter, so this is simply the sum of the largest ratings.
+#[cfg(test)]
+fn max_bank_sum(batteries: &[usize], num_picks: usize) -> Result<usize> {
+    if batteries.len() < num_picks {
+        bail!(
+            "Bank needs at least {} batteries but only has {}",
+            num_picks,
+            batteries.len()
+        );
+    }
+
+    let mut sorted = batteries.to_vec();
+    sorted.sort_unstable_by_key(|&rating| Reverse(rating));
+    Ok(sorted.iter().take(num_picks).sum())
+}
+
+/// Render the digits of the largest possible `num_picks`-digit number, e.g. for explaining which
+/// batteries were chosen.
+#[cfg(test)]
+fn max_bank_string(batteries: &[usize], num_picks: usize) -> Result<String> {
+    use anyhow::Context;
+
+    max_bank_selection(batteries, num_picks)?
+        .into_iter()
+        .map(|idx| {
+            char::from_digit(batteries[idx] as u32, MAX_RADIX)
+                .with_context(|| format!("Battery rating {} has no digit", batteries[idx]))
+        })
+        .collect()
+}
+
+/// Sum the highest `num_picks`-digit values obtainable from each bank, one bank after another.
+fn sum_joltages_serial(banks: &[Vec<usize>], num_picks: usize, base: u32) -> Result<usize> {
     banks.iter().try_fold(0usize, |acc, bank| {
-        Ok(acc + max_bank_joltage(bank, NUM_PICKS_A)?)
+        Ok(acc + max_bank_joltage(bank, num_picks, base)?)
     })
 }
 
+/// Sum the highest `num_picks`-digit values obtainable from each bank. Large inputs are split into
+/// one chunk per thread, and the first error in input order is reported.
+fn sum_joltages(banks: &[Vec<usize>], num_picks: usize, base: u32) -> Result<usize> {
+    if banks.len() < PARALLEL_THRESHOLD {
+        return sum_joltages_serial(banks, num_picks, base);
+    }
+
+    let num_workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = banks.len().div_ceil(num_workers);
+    let partial_sums: Vec<Result<usize>> = thread::scope(|scope| {
+        let workers: Vec<_> = banks
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || sum_joltages_serial(chunk, num_picks, base)))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Bank solver panicked"))
+            .collect()
+    });
+    partial_sums
+        .into_iter()
+        .try_fold(0usize, |acc, sum| Ok(acc + sum?))
+}
+
+/// Sum the highest two-digit values obtainable from each bank.
+fn part_a(banks: &[Vec<usize>], base: u32) -> Result<usize> {
+    sum_joltages(banks, NUM_PICKS_A, base)
+}
+
 /// Sum the highest 12-digit values obtainable from each bank.
-fn part_b(banks: &[Vec<usize>]) -> Result<usize> {
-    banks.iter().try_fold(0usize, |acc, bank| {
-        Ok(acc + max_bank_joltage(bank, NUM_PICKS_B)?)
-    })
+fn part_b(banks: &[Vec<usize>], base: u32) -> Result<usize> {
+    sum_joltages(banks, NUM_PICKS_B, base)
+}
+
+/// Sum the highest two-digit and 12-digit values obtainable from each bank. The greedy selection
+/// has to run once per digit count because the best two digits aren't necessarily a prefix of the
+/// best 12 digits; a large digit near the end of a bank can only be picked early when few digits
+/// are picked.
+fn solve(banks: &[Vec<usize>], base: u32) -> Result<(usize, usize)> {
+    Ok((part_a(banks, base)?, part_b(banks, base)?))
 }
 
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let banks = parse_input(input)?;
-    Ok((part_a(&banks)?, Some(part_b(&banks)?)))
+    let banks = parse_input(input, DECIMAL_BASE, false)?;
+    let (a, b) = solve(&banks, DECIMAL_BASE)?;
+    Ok((a, Some(b)))
 }
 
 #[cfg(test)]
@@ -102,13 +267,148 @@ mod test {
 
     #[test]
     fn example_a() {
-        let banks = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_a(&banks).unwrap(), 357);
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        assert_eq!(part_a(&banks, DECIMAL_BASE).unwrap(), 357);
     }
 
     #[test]
     fn example_b() {
-        let banks = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_b(&banks).unwrap(), 3_121_910_778_619);
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        assert_eq!(part_b(&banks, DECIMAL_BASE).unwrap(), 3_121_910_778_619);
+    }
+
+    #[test]
+    fn combined() {
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        assert_eq!(
+            solve(&banks, DECIMAL_BASE).unwrap(),
+            (357, 3_121_910_778_619)
+        );
+
+        let bank = parse_input("81111111111119", DECIMAL_BASE, false)
+            .unwrap()
+            .remove(0);
+        assert_eq!(max_bank_string(&bank, NUM_PICKS_A).unwrap(), "89");
+        assert_eq!(max_bank_string(&bank, NUM_PICKS_B).unwrap(), "811111111119");
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let input = std::fs::read_to_string("data/day3.txt").unwrap();
+        let banks = parse_input(&input, DECIMAL_BASE, false).unwrap();
+        let many_banks: Vec<_> = banks
+            .iter()
+            .cycle()
+            .take(PARALLEL_THRESHOLD + 1)
+            .cloned()
+            .collect();
+        for num_picks in [NUM_PICKS_A, NUM_PICKS_B] {
+            assert_eq!(
+                sum_joltages(&many_banks, num_picks, DECIMAL_BASE).unwrap(),
+                sum_joltages_serial(&many_banks, num_picks, DECIMAL_BASE).unwrap()
+            );
+        }
+
+        let mut invalid_banks = many_banks;
+        invalid_banks[PARALLEL_THRESHOLD] = vec![1];
+        assert!(sum_joltages(&invalid_banks, NUM_PICKS_A, DECIMAL_BASE).is_err());
+    }
+
+    #[test]
+    fn selected_digits() {
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        assert_eq!(max_bank_string(&banks[0], NUM_PICKS_A).unwrap(), "98");
+        for bank in &banks {
+            for num_picks in [NUM_PICKS_A, NUM_PICKS_B] {
+                assert_eq!(
+                    max_bank_string(bank, num_picks).unwrap(),
+                    max_bank_joltage(bank, num_picks, DECIMAL_BASE)
+                        .unwrap()
+                        .to_string()
+                );
+            }
+        }
+        assert!(max_bank_string(&banks[0], 100).is_err());
+    }
+
+    #[test]
+    fn largest_sums() {
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        let sums = |num_picks| {
+            banks
+                .iter()
+                .map(|bank| max_bank_sum(bank, num_picks).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sums(NUM_PICKS_A), vec![17, 17, 15, 17]);
+        assert_eq!(sums(NUM_PICKS_B), vec![48, 27, 47, 42]);
+
+        // The order of the batteries only matters when concatenating them
+        assert_eq!(
+            max_bank_joltage(&banks[1], NUM_PICKS_A, DECIMAL_BASE).unwrap(),
+            89
+        );
+        assert_eq!(max_bank_sum(&banks[1], NUM_PICKS_A).unwrap(), 17);
+        assert!(max_bank_sum(&banks[0], 100).is_err());
+    }
+
+    #[test]
+    fn hexadecimal() {
+        let banks = parse_input("1f2e\n9a1b", 16, false).unwrap();
+        assert_eq!(part_a(&banks, 16).unwrap(), 0xfe + 0xab);
+        assert!(parse_input("1f0e", 16, false).is_err());
+        assert!(parse_input("1g2e", 16, false).is_err());
+
+        let err = parse_input("1f2e", 37, false).unwrap_err();
+        assert_eq!(err.to_string(), "Base 37 must be between 2 and 36");
+        assert!(parse_input("1", 1, false).is_err());
+    }
+
+    #[test]
+    fn strict_bank_lengths() {
+        assert!(parse_input(EXAMPLE_INPUT, DECIMAL_BASE, true).is_ok());
+
+        let ragged = "1234\n5678\n912\n3456";
+        assert!(parse_input(ragged, DECIMAL_BASE, false).is_ok());
+        let err = parse_input(ragged, DECIMAL_BASE, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Bank on line 3 has 3 batteries but line 1 has 4"
+        );
+    }
+
+    #[test]
+    fn top_joltages() {
+        let banks = parse_input(EXAMPLE_INPUT, DECIMAL_BASE, false).unwrap();
+        assert_eq!(
+            top_bank_joltages(&banks[0], NUM_PICKS_A, DECIMAL_BASE, 3).unwrap(),
+            vec![98, 97, 96]
+        );
+        assert_eq!(
+            top_bank_joltages(&banks[1], NUM_PICKS_A, DECIMAL_BASE, 3).unwrap(),
+            vec![89, 81, 19]
+        );
+        assert_eq!(
+            top_bank_joltages(&banks[1], NUM_PICKS_A, DECIMAL_BASE, 10).unwrap(),
+            vec![89, 81, 19, 11]
+        );
+        for bank in &banks {
+            assert_eq!(
+                top_bank_joltages(bank, NUM_PICKS_B, DECIMAL_BASE, 1).unwrap(),
+                vec![max_bank_joltage(bank, NUM_PICKS_B, DECIMAL_BASE).unwrap()]
+            );
+        }
+        assert!(
+            top_bank_joltages(&banks[0], NUM_PICKS_A, DECIMAL_BASE, 0)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(top_bank_joltages(&banks[0], 100, DECIMAL_BASE, 3).is_err());
+
+        let banks = parse_input("1f2e", 16, false).unwrap();
+        assert_eq!(
+            top_bank_joltages(&banks[0], NUM_PICKS_A, 16, 3).unwrap(),
+            vec![0xfe, 0xf2, 0x2e]
+        );
     }
 }
@@ -1,8 +1,12 @@
 //! # Day 4: Printing Department
-//! Input is a rectangular grid of `@` (paper rolls) and `.` (empty).
+//! Input is a rectangular grid of `@` or `#` (paper rolls) and `.` (empty). The grid may optionally
+//! be treated as toroidal, where cells on opposite edges neighbor each other. Rolls may also be
+//! placed on a hexagonal grid where every odd row is shifted half a cell to the right, giving each
+//! cell six neighbors instead of eight. Cells outside the grid may also be treated as occupied, as
+//! if the room were walled in by rolls.
 //!
 //! ## Part A
-//! A roll is accessible when fewer than four of its eight neighbors also contain rolls; count all
+//! A roll is accessible when fewer than four of its neighbors also contain rolls; count all
 //! accessible rolls.
 //!
 //! ## Part B
@@ -12,6 +16,8 @@
 use anyhow::{Result, bail};
 use std::collections::{HashMap, HashSet};
 
+pub const TITLE: &str = "Printing Department";
+
 /// Maximum number of rolls in neighboring cells that still permits access.
 const ACCESS_THRESHOLD: usize = 4;
 
@@ -21,30 +27,124 @@ struct Cell {
     y: isize,
 }
 
+/// Which cells count as neighbors
+#[derive(Debug, Clone, Copy, Default)]
+enum Connectivity {
+    /// The eight surrounding cells of a square grid, including diagonals
+    #[default]
+    Square,
+    /// The six surrounding cells of a hexagonal grid where odd rows are shifted right
+    Hex,
+}
+
+/// Neighbor offsets as `(dx, dy)` on a square grid
+const SQUARE_DELTAS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Neighbor offsets as `(dx, dy)` for cells on even rows of a hexagonal grid
+const HEX_EVEN_DELTAS: [(isize, isize); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+
+/// Neighbor offsets as `(dx, dy)` for cells on odd rows of a hexagonal grid
+const HEX_ODD_DELTAS: [(isize, isize); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+/// Grid variations accepted by `parse_input`
+#[derive(Debug, Clone, Copy, Default)]
+struct ParseOptions {
+    /// Whether cells on opposite edges neighbor each other
+    wrap: bool,
+    connectivity: Connectivity,
+    /// Whether every cell outside the grid counts as a roll
+    border_occupied: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Grid {
+    width: isize,
+    height: isize,
+    wrap: bool,
+    connectivity: Connectivity,
+    border_occupied: bool,
+}
+
+impl Grid {
+    /// Check whether the cell is within the grid bounds.
+    fn contains(self, cell: Cell) -> bool {
+        (0..self.width).contains(&cell.x) && (0..self.height).contains(&cell.y)
+    }
+}
+
 impl Cell {
-    /// Return all eight neighboring cells (including diagonals).
-    fn neighbors(self) -> impl Iterator<Item = Cell> {
-        (-1..=1).flat_map(move |dy| {
-            (-1..=1)
-                .filter(move |&dx| dx != 0 || dy != 0)
-                .map(move |dx| Cell {
-                    x: self.x + dx,
-                    y: self.y + dy,
-                })
+    /// Return all neighboring cells for the grid's connectivity, wrapping around the edges when the
+    /// grid is toroidal. A toroidal hexagonal grid needs an even height for rows to line up.
+    fn neighbors(self, grid: Grid) -> impl Iterator<Item = Cell> {
+        let deltas: &[(isize, isize)] = match grid.connectivity {
+            Connectivity::Square => &SQUARE_DELTAS,
+            Connectivity::Hex if self.y.rem_euclid(2) == 0 => &HEX_EVEN_DELTAS,
+            Connectivity::Hex => &HEX_ODD_DELTAS,
+        };
+        deltas.iter().map(move |&(dx, dy)| {
+            let (x, y) = (self.x + dx, self.y + dy);
+            if grid.wrap {
+                Cell {
+                    x: x.rem_euclid(grid.width),
+                    y: y.rem_euclid(grid.height),
+                }
+            } else {
+                Cell { x, y }
+            }
         })
     }
 }
 
-/// Parse a grid of `@` rolls and `.` empty spaces into neighbor counts for each roll.
-fn parse_input(input: &str) -> Result<HashMap<Cell, usize>> {
+/// Count the rolls in the cells neighboring `cell`, including cells outside the grid when its
+/// border is occupied.
+fn neighbor_count(rolls: &HashSet<Cell>, cell: Cell, grid: Grid) -> usize {
+    cell.neighbors(grid)
+        .filter(|&neighbor| {
+            rolls.contains(&neighbor) || (grid.border_occupied && !grid.contains(neighbor))
+        })
+        .count()
+}
+
+/// Check whether `cell` holds a roll with fewer than four neighboring rolls in the given roll set.
+fn is_accessible(rolls: &HashSet<Cell>, cell: Cell, grid: Grid) -> bool {
+    rolls.contains(&cell) && neighbor_count(rolls, cell, grid) < ACCESS_THRESHOLD
+}
+
+/// Parse a grid of `@` or `#` rolls and `.` empty spaces into the grid dimensions and the cells
+/// holding a roll. An occupied border has no effect on a toroidal grid, and a toroidal hexagonal
+/// grid must have an even height.
+fn parse_input(input: &str, options: ParseOptions) -> Result<(Grid, HashSet<Cell>)> {
+    let ParseOptions {
+        wrap,
+        connectivity,
+        border_occupied,
+    } = options;
     let mut rolls = HashSet::new();
+    let mut grid = Grid {
+        width: 0,
+        height: 0,
+        wrap,
+        connectivity,
+        border_occupied,
+    };
     for (y, line) in input.trim().lines().enumerate() {
+        grid.height = grid.height.max(y as isize + 1);
+        grid.width = grid.width.max(line.len() as isize);
         for (x, c) in line.chars().enumerate() {
             match c {
-                '@' => {
+                '@' | '#' => {
                     rolls.insert(Cell {
-                        x: y as isize,
-                        y: x as isize,
+                        x: x as isize,
+                        y: y as isize,
                     });
                 }
                 '.' => {}
@@ -53,28 +153,39 @@ fn parse_input(input: &str) -> Result<HashMap<Cell, usize>> {
         }
     }
 
-    Ok(rolls
+    // Wrapping an odd number of rows would place two unshifted rows next to each other
+    if wrap && matches!(connectivity, Connectivity::Hex) && grid.height % 2 == 1 {
+        bail!(
+            "A toroidal hexagonal grid needs an even height, got {}",
+            grid.height
+        );
+    }
+
+    Ok((grid, rolls))
+}
+
+/// Return the number of neighboring rolls for each roll.
+fn neighbor_counts(rolls: &HashSet<Cell>, grid: Grid) -> HashMap<Cell, usize> {
+    rolls
         .iter()
-        .map(|&cell| {
-            let count = cell
-                .neighbors()
-                .filter(|neighbor| rolls.contains(neighbor))
-                .count();
-            (cell, count)
-        })
-        .collect())
+        .map(|&cell| (cell, neighbor_count(rolls, cell, grid)))
+        .collect()
 }
 
 /// Count rolls with fewer than four neighboring rolls.
-fn part_a(num_neighbors: &HashMap<Cell, usize>) -> usize {
-    num_neighbors
-        .values()
-        .filter(|&&count| count < ACCESS_THRESHOLD)
+fn part_a(grid: Grid, rolls: &HashSet<Cell>) -> usize {
+    rolls
+        .iter()
+        .filter(|&&cell| is_accessible(rolls, cell, grid))
         .count()
 }
 
-/// Remove accessible rolls until no more become accessible; return the total removed.
-fn part_b(mut num_neighbors: HashMap<Cell, usize>) -> usize {
+/// Remove accessible rolls until no more become accessible. Return the total removed along with the
+/// surviving rolls and their final neighbor counts.
+fn remove_accessible(
+    grid: Grid,
+    mut num_neighbors: HashMap<Cell, usize>,
+) -> (usize, HashMap<Cell, usize>) {
     let mut queue: Vec<Cell> = num_neighbors
         .iter()
         .filter_map(|(&coord, &count)| (count < ACCESS_THRESHOLD).then_some(coord))
@@ -87,7 +198,7 @@ fn part_b(mut num_neighbors: HashMap<Cell, usize>) -> usize {
         }
         num_removed += 1;
 
-        for neighbor in cell.neighbors() {
+        for neighbor in cell.neighbors(grid) {
             if let Some(count) = num_neighbors.get_mut(&neighbor) {
                 *count -= 1;
                 if *count < ACCESS_THRESHOLD {
@@ -97,12 +208,91 @@ fn part_b(mut num_neighbors: HashMap<Cell, usize>) -> usize {
         }
     }
 
+    (num_removed, num_neighbors)
+}
+
+/// Remove every accessible roll at once in waves, stopping after `max_rounds` waves when given.
+/// Return how many rolls were removed. Without a limit this removes as many rolls as `part_b`.
+#[cfg(test)]
+fn remove_bounded(
+    grid: Grid,
+    mut num_neighbors: HashMap<Cell, usize>,
+    max_rounds: Option<usize>,
+) -> usize {
+    let mut wave: Vec<Cell> = num_neighbors
+        .iter()
+        .filter_map(|(&coord, &count)| (count < ACCESS_THRESHOLD).then_some(coord))
+        .collect();
+
+    let mut num_removed = 0;
+    let mut rounds = 0;
+    while !wave.is_empty() && max_rounds.is_none_or(|max_rounds| rounds < max_rounds) {
+        rounds += 1;
+        num_removed += wave.len();
+        for cell in &wave {
+            num_neighbors.remove(cell);
+        }
+
+        // Rolls in the current wave are gone, so only the survivors around them lose a neighbor
+        let mut next_wave = HashSet::new();
+        for cell in wave {
+            for neighbor in cell.neighbors(grid) {
+                if let Some(count) = num_neighbors.get_mut(&neighbor) {
+                    *count -= 1;
+                    if *count < ACCESS_THRESHOLD {
+                        next_wave.insert(neighbor);
+                    }
+                }
+            }
+        }
+        wave = next_wave.into_iter().collect();
+    }
+
     num_removed
 }
 
+/// Remove accessible rolls until no more become accessible; return the total removed.
+fn part_b(grid: Grid, rolls: &HashSet<Cell>) -> usize {
+    remove_accessible(grid, neighbor_counts(rolls, grid)).0
+}
+
+/// Count connected regions of rolls where neighboring rolls belong to the same region.
+#[cfg(test)]
+fn region_count(grid: Grid, num_neighbors: &HashMap<Cell, usize>) -> usize {
+    let mut unvisited: HashSet<Cell> = num_neighbors.keys().copied().collect();
+    let mut regions = 0;
+    while let Some(&start) = unvisited.iter().next() {
+        unvisited.remove(&start);
+        regions += 1;
+
+        let mut stack = vec![start];
+        while let Some(cell) = stack.pop() {
+            stack.extend(
+                cell.neighbors(grid)
+                    .filter(|neighbor| unvisited.remove(neighbor)),
+            );
+        }
+    }
+    regions
+}
+
+/// Solve both parts for a grid parsed with the given options.
+fn main_with(input: &str, options: ParseOptions) -> Result<(usize, Option<usize>)> {
+    let (grid, rolls) = parse_input(input, options)?;
+    Ok((part_a(grid, &rolls), Some(part_b(grid, &rolls))))
+}
+
+/// Solve both parts with the rolls placed on a hexagonal grid.
+pub fn main_hex(input: &str) -> Result<(usize, Option<usize>)> {
+    let options = ParseOptions {
+        connectivity: Connectivity::Hex,
+        ..ParseOptions::default()
+    };
+    main_with(input, options)
+}
+
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let num_neighbors = parse_input(input)?;
-    Ok((part_a(&num_neighbors), Some(part_b(num_neighbors.clone()))))
+    main_with(input, ParseOptions::default())
 }
 
 #[cfg(test)]
@@ -130,13 +320,190 @@ mod test {
 
     #[test]
     fn example_a() {
-        let neighbors = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_a(&neighbors), 13);
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(grid, &rolls), 13);
     }
 
     #[test]
     fn example_b() {
-        let neighbors = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_b(neighbors), 43);
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        assert_eq!(part_b(grid, &rolls), 43);
+    }
+
+    #[test]
+    fn hash_rolls() {
+        let input = EXAMPLE_INPUT.replace('@', "#");
+        let (grid, rolls) = parse_input(&input, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(grid, &rolls), 13);
+        assert_eq!(part_b(grid, &rolls), 43);
+        assert!(parse_input("@#\n.x", ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn accessibility_threshold() {
+        let input = dedent!(
+            r#"
+                @@@
+                .@@
+                ...
+            "#
+        );
+        let (grid, rolls) = parse_input(input, ParseOptions::default()).unwrap();
+        // The center roll has exactly four neighbors, one too many to be accessible
+        assert!(!is_accessible(&rolls, Cell { x: 1, y: 1 }, grid));
+        assert!(is_accessible(&rolls, Cell { x: 0, y: 0 }, grid));
+        assert!(!is_accessible(&rolls, Cell { x: 0, y: 1 }, grid));
+        assert_eq!(part_a(grid, &rolls), 3);
+
+        let rolls: HashSet<Cell> = rolls
+            .into_iter()
+            .filter(|&cell| cell != Cell { x: 2, y: 1 })
+            .collect();
+        assert!(is_accessible(&rolls, Cell { x: 1, y: 1 }, grid));
+    }
+
+    #[test]
+    fn bounded_rounds() {
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        let neighbors = neighbor_counts(&rolls, grid);
+        let removed: Vec<_> = (0..=10)
+            .map(|rounds| remove_bounded(grid, neighbors.clone(), Some(rounds)))
+            .collect();
+        assert_eq!(removed, vec![0, 13, 25, 32, 37, 39, 40, 41, 42, 43, 43]);
+        assert_eq!(remove_bounded(grid, neighbors, None), 43);
+
+        let input = std::fs::read_to_string("data/day4.txt").unwrap();
+        let (grid, rolls) = parse_input(&input, ParseOptions::default()).unwrap();
+        assert_eq!(
+            remove_bounded(grid, neighbor_counts(&rolls, grid), None),
+            8946
+        );
+    }
+
+    #[test]
+    fn survivors() {
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        let (num_removed, survivors) = remove_accessible(grid, neighbor_counts(&rolls, grid));
+        assert_eq!(num_removed, 43);
+        assert_eq!(survivors.len(), rolls.len() - 43);
+        assert!(survivors.values().all(|&count| count >= ACCESS_THRESHOLD));
+    }
+
+    #[test]
+    fn regions() {
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        assert_eq!(region_count(grid, &neighbor_counts(&rolls, grid)), 1);
+
+        let input = dedent!(
+            r#"
+                @..@
+                ....
+                .@@.
+            "#
+        );
+        let (grid, rolls) = parse_input(input, ParseOptions::default()).unwrap();
+        assert_eq!(region_count(grid, &neighbor_counts(&rolls, grid)), 3);
+    }
+
+    #[test]
+    fn hex() {
+        let input = dedent!(
+            r#"
+                @@@
+                @@@
+                @@@
+            "#
+        );
+        let (grid, rolls) = parse_input(input, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(grid, &rolls), 4);
+        assert_eq!(neighbor_counts(&rolls, grid)[&Cell { x: 2, y: 1 }], 5);
+
+        // The odd middle row is shifted right, so its last roll only touches three others
+        let hex = ParseOptions {
+            connectivity: Connectivity::Hex,
+            ..ParseOptions::default()
+        };
+        let (grid, rolls) = parse_input(input, hex).unwrap();
+        let neighbors = neighbor_counts(&rolls, grid);
+        assert_eq!(neighbors[&Cell { x: 1, y: 1 }], 6);
+        assert_eq!(neighbors[&Cell { x: 1, y: 0 }], 4);
+        assert_eq!(neighbors[&Cell { x: 0, y: 1 }], 5);
+        assert_eq!(neighbors[&Cell { x: 2, y: 1 }], 3);
+        assert_eq!(part_a(grid, &rolls), 5);
+        assert_eq!(region_count(grid, &neighbors), 1);
+        assert_eq!(part_b(grid, &rolls), 9);
+
+        let hex_wrap = ParseOptions { wrap: true, ..hex };
+        assert!(parse_input(input, hex_wrap).is_err());
+        let input = format!("{input}\n@@@");
+        let (grid, rolls) = parse_input(&input, hex_wrap).unwrap();
+        assert!(
+            neighbor_counts(&rolls, grid)
+                .values()
+                .all(|&count| count == 6)
+        );
+    }
+
+    #[test]
+    fn toroidal() {
+        let input = dedent!(
+            r#"
+                @.@
+                @.@
+                @.@
+            "#
+        );
+        let (grid, rolls) = parse_input(input, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(grid, &rolls), 6);
+
+        let wrap = ParseOptions {
+            wrap: true,
+            ..ParseOptions::default()
+        };
+        let (grid, rolls) = parse_input(input, wrap).unwrap();
+        assert_eq!(part_a(grid, &rolls), 0);
+        assert_eq!(region_count(grid, &neighbor_counts(&rolls, grid)), 1);
+        assert_eq!(part_b(grid, &rolls), 0);
+    }
+
+    #[test]
+    fn occupied_border() {
+        let walled = ParseOptions {
+            border_occupied: true,
+            ..ParseOptions::default()
+        };
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, walled).unwrap();
+        // Every roll along the walls gains neighbors, leaving only one accessible roll inside
+        assert_eq!(part_a(grid, &rolls), 1);
+        let neighbors = neighbor_counts(&rolls, grid);
+        assert_eq!(neighbors[&Cell { x: 6, y: 2 }], 2);
+        assert_eq!(neighbors[&Cell { x: 0, y: 1 }], 6);
+        assert_eq!(part_b(grid, &rolls), 2);
+
+        let rolls: HashSet<Cell> = [Cell { x: 1, y: 1 }].into();
+        let (grid, _) = parse_input("...\n.@.\n...", walled).unwrap();
+        assert!(is_accessible(&rolls, Cell { x: 1, y: 1 }, grid));
+        let (grid, _) = parse_input("@..\n...", walled).unwrap();
+        assert!(!is_accessible(
+            &[Cell { x: 0, y: 0 }].into(),
+            Cell { x: 0, y: 0 },
+            grid
+        ));
+
+        // Nothing is outside a toroidal grid
+        let wrap = ParseOptions {
+            wrap: true,
+            ..ParseOptions::default()
+        };
+        let walled_wrap = ParseOptions {
+            wrap: true,
+            ..walled
+        };
+        let (grid, rolls) = parse_input(EXAMPLE_INPUT, walled_wrap).unwrap();
+        let (wrapped_grid, wrapped) = parse_input(EXAMPLE_INPUT, wrap).unwrap();
+        assert_eq!(
+            neighbor_counts(&rolls, grid),
+            neighbor_counts(&wrapped, wrapped_grid)
+        );
     }
 }
@@ -1,9 +1,10 @@
 //! # Day 5: Cafeteria
 //! Input lists inclusive fresh ingredient ID ranges, then a blank line, followed by ingredient IDs
-//! to evaluate.
+//! to evaluate. Ranges are listed one per line, comma-separated on a line, or a mix of both.
 //!
 //! ## Part A
-//! Count how many available ingredient IDs fall within any listed fresh range.
+//! Count how many available ingredient IDs fall within any listed fresh range. An ID listed more
+//! than once is counted every time it's listed.
 //!
 //! ## Part B
 //! Count how many distinct ingredient IDs are covered by the fresh ranges.
@@ -11,7 +12,13 @@ use std::ops::Range;
 
 use anyhow::{Context, Result, bail};
 
-fn parse_input(input: &str) -> Result<(Vec<Range<usize>>, Vec<usize>)> {
+pub const TITLE: &str = "Cafeteria";
+
+type FreshRanges = Vec<Range<usize>>;
+
+/// Parse fresh ranges and ingredient IDs. Return the sorted and merged ranges along with the ranges
+/// as listed and the IDs.
+fn parse_input(input: &str) -> Result<(FreshRanges, FreshRanges, Vec<usize>)> {
     let mut ranges = Vec::new();
     let mut ids = Vec::new();
     let mut lines = input.trim().lines().enumerate();
@@ -23,19 +30,21 @@ fn parse_input(input: &str) -> Result<(Vec<Range<usize>>, Vec<usize>)> {
             break;
         }
 
-        let (start, end) = line
-            .split_once('-')
-            .with_context(|| format!("Missing dash in range on line {}", line_no))?;
-        let start = start
-            .parse::<usize>()
-            .with_context(|| format!("Invalid range start on line {}", line_no))?;
-        let end_inclusive = end
-            .parse::<usize>()
-            .with_context(|| format!("Invalid range end on line {}", line_no))?;
-        if start > end_inclusive {
-            bail!("Range start exceeds end on line {}", line_no);
+        for range in line.split(',') {
+            let (start, end) = range
+                .split_once('-')
+                .with_context(|| format!("Missing dash in range on line {}", line_no))?;
+            let start = start
+                .parse::<usize>()
+                .with_context(|| format!("Invalid range start on line {}", line_no))?;
+            let end_inclusive = end
+                .parse::<usize>()
+                .with_context(|| format!("Invalid range end on line {}", line_no))?;
+            if start > end_inclusive {
+                bail!("Range start exceeds end on line {}", line_no);
+            }
+            ranges.push(start..(end_inclusive + 1));
         }
-        ranges.push(start..(end_inclusive + 1));
     }
 
     for (idx, line) in lines {
@@ -45,30 +54,64 @@ fn parse_input(input: &str) -> Result<(Vec<Range<usize>>, Vec<usize>)> {
         );
     }
 
-    let mut ranges_sorted = ranges;
+    let (merged_ranges, _) = merge_ranges(&ranges);
+    Ok((merged_ranges, ranges, ids))
+}
+
+/// Sort and merge overlapping or adjacent fresh ranges. Return the merged ranges along with how
+/// many of the listed ranges were absorbed into another one, as a measure of how much they overlap.
+fn merge_ranges(ranges: &[Range<usize>]) -> (FreshRanges, usize) {
+    let mut ranges_sorted = ranges.to_vec();
     ranges_sorted.sort_unstable_by_key(|range| range.start);
     let mut merged_ranges: Vec<Range<usize>> = Vec::with_capacity(ranges_sorted.len());
+    let mut num_merges = 0;
     for range in ranges_sorted {
         if let Some(last) = merged_ranges.last_mut()
             && range.start <= last.end
         {
             last.end = last.end.max(range.end);
+            num_merges += 1;
             continue;
         }
         merged_ranges.push(range);
     }
+    (merged_ranges, num_merges)
+}
+
+/// Count how many of the listed (unmerged) fresh ranges contain the ID.
+#[cfg(test)]
+fn coverage_depth(unmerged: &[Range<usize>], id: usize) -> usize {
+    unmerged.iter().filter(|range| range.contains(&id)).count()
+}
 
-    Ok((merged_ranges, ids))
+/// Check whether the ID is contained in any of the sorted and merged fresh ranges.
+fn is_fresh(ranges: &[Range<usize>], id: usize) -> bool {
+    let idx = ranges.partition_point(|range| range.end <= id);
+    idx < ranges.len() && ranges[idx].contains(&id)
 }
 
-/// Count ingredient IDs that are contained in any fresh range.
+/// Count ingredient IDs that are contained in any fresh range, including repeated IDs.
 fn part_a(ranges: &[Range<usize>], ids: &[usize]) -> usize {
-    ids.iter()
-        .filter(|&&id| {
-            let idx = ranges.partition_point(|range| range.end <= id);
-            idx < ranges.len() && ranges[idx].contains(&id)
-        })
-        .count()
+    ids.iter().filter(|&&id| is_fresh(ranges, id)).count()
+}
+
+/// Lazily pair every ID with whether it's contained in any of the sorted and merged fresh ranges,
+/// keeping the order the IDs are given in.
+#[cfg(test)]
+fn classify<'a>(
+    ranges: &'a [Range<usize>],
+    ids: impl Iterator<Item = usize> + 'a,
+) -> impl Iterator<Item = (usize, bool)> + 'a {
+    ids.map(|id| (id, is_fresh(ranges, id)))
+}
+
+/// Count distinct ingredient IDs that are contained in any fresh range, ignoring repeated IDs.
+#[cfg(test)]
+fn count_unique_fresh(ranges: &[Range<usize>], ids: &[usize]) -> usize {
+    let mut unique = ids.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    part_a(ranges, &unique)
 }
 
 /// Return the total number of unique ingredient IDs covered by any fresh range.
@@ -76,8 +119,42 @@ fn part_b(ranges: &[Range<usize>]) -> usize {
     ranges.iter().map(Range::len).sum()
 }
 
+/// Return the longest of the sorted and merged fresh ranges, preferring the earliest on ties.
+#[cfg(test)]
+fn largest_fresh_block(ranges: &[Range<usize>]) -> Option<Range<usize>> {
+    ranges.iter().cloned().reduce(|best, range| {
+        if range.len() > best.len() {
+            range
+        } else {
+            best
+        }
+    })
+}
+
+/// Intersect two sorted lists of non-overlapping ranges, returning the ranges covered by both.
+#[cfg(test)]
+fn intersect(a: &[Range<usize>], b: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut intersection = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].start.max(b[j].start);
+        let end = a[i].end.min(b[j].end);
+        if start < end {
+            intersection.push(start..end);
+        }
+
+        // Advance whichever range ends first as it can't overlap anything further in the other list
+        if a[i].end <= b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    intersection
+}
+
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let (ranges, ids) = parse_input(input)?;
+    let (ranges, _, ids) = parse_input(input)?;
     Ok((part_a(&ranges, &ids), Some(part_b(&ranges))))
 }
 
@@ -107,27 +184,138 @@ mod test {
 
     #[test]
     fn example_a() {
-        let (ranges, ids) = parse_input(EXAMPLE_INPUT).unwrap();
+        let (ranges, _, ids) = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(part_a(&ranges, &ids), 3);
     }
 
     #[test]
     fn example_b() {
-        let (ranges, _) = parse_input(EXAMPLE_INPUT).unwrap();
+        let (ranges, _, _) = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(part_b(&ranges), 14);
     }
 
+    #[test]
+    fn classify_stream() {
+        let (ranges, _, ids) = parse_input(EXAMPLE_INPUT).unwrap();
+        let classified: Vec<_> = classify(&ranges, ids.iter().copied()).collect();
+        assert_eq!(
+            classified,
+            vec![
+                (1, false),
+                (5, true),
+                (8, false),
+                (11, true),
+                (17, true),
+                (32, false)
+            ]
+        );
+        let fresh = classified.iter().filter(|&&(_, fresh)| fresh).count();
+        assert_eq!(fresh, part_a(&ranges, &ids));
+
+        // Doesn't need to collect the IDs up front
+        let mut unbounded = classify(&ranges, 0..);
+        assert_eq!(unbounded.nth(20), Some((20, true)));
+        assert_eq!(unbounded.next(), Some((21, false)));
+    }
+
+    #[test]
+    fn coverage() {
+        let (_, unmerged, ids) = parse_input(EXAMPLE_INPUT).unwrap();
+        let depths: Vec<_> = ids
+            .iter()
+            .map(|&id| coverage_depth(&unmerged, id))
+            .collect();
+        assert_eq!(depths, vec![0, 1, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let (ranges, _, ids) = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(count_unique_fresh(&ranges, &ids), part_a(&ranges, &ids));
+
+        let (ranges, _, ids) = parse_input("3-5\n\n5\n4\n5\n8\n8").unwrap();
+        assert_eq!(part_a(&ranges, &ids), 3);
+        assert_eq!(count_unique_fresh(&ranges, &ids), 2);
+    }
+
+    #[test]
+    fn comma_separated_ranges() {
+        let input = dedent!(
+            r#"
+                3-5,10-14
+                16-20,12-18
+
+                1
+                5
+                8
+                11
+                17
+                32
+            "#
+        );
+        assert_eq!(
+            parse_input(input).unwrap(),
+            parse_input(EXAMPLE_INPUT).unwrap()
+        );
+        assert!(parse_input("3-5,\n\n1").is_err());
+    }
+
+    #[test]
+    fn merge_count() {
+        let (merged, unmerged, _) = parse_input(EXAMPLE_INPUT).unwrap();
+        // 12-18 bridges 10-14 and 16-20 into a single range
+        assert_eq!(merge_ranges(&unmerged), (merged, 2));
+
+        let (_, unmerged, _) = parse_input("1-3\n5-7\n4-4\n6-6\n10-12").unwrap();
+        assert_eq!(merge_ranges(&unmerged), (vec![1..8, 10..13], 3));
+        assert_eq!(merge_ranges(&[]), (vec![], 0));
+    }
+
+    #[test]
+    fn largest_block() {
+        let (merged, _, _) = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(largest_fresh_block(&merged), Some(10..21));
+        assert_eq!(largest_fresh_block(&ranges("1-3\n5-7")), Some(1..4));
+        assert_eq!(largest_fresh_block(&[]), None);
+    }
+
+    fn ranges(input: &str) -> Vec<Range<usize>> {
+        parse_input(input).unwrap().0
+    }
+
+    #[test]
+    fn intersect_disjoint() {
+        assert!(intersect(&ranges("1-2\n10-11"), &ranges("4-7")).is_empty());
+        assert!(intersect(&ranges("1-4"), &[]).is_empty());
+    }
+
+    #[test]
+    fn intersect_nested() {
+        assert_eq!(
+            intersect(&ranges("1-19"), &ranges("3-4\n8-8")),
+            ranges("3-4\n8-8")
+        );
+    }
+
+    #[test]
+    fn intersect_partial_overlap() {
+        assert_eq!(
+            intersect(&ranges("1-4\n8-11"), &ranges("3-9\n11-14")),
+            vec![3..5, 8..10, 11..12]
+        );
+    }
+
     #[test]
     fn accepts_ranges_only() {
         let input = "1-3\n5-5\n";
-        let (ranges, ids) = parse_input(input).unwrap();
+        let (ranges, _, ids) = parse_input(input).unwrap();
         assert_eq!(ids.len(), 0);
         assert_eq!(part_b(&ranges), 4);
     }
 
     #[test]
     fn accepts_empty_input() {
-        let (ranges, ids) = parse_input("").unwrap();
+        let (ranges, _, ids) = parse_input("").unwrap();
         assert!(ranges.is_empty());
         assert!(ids.is_empty());
         assert_eq!(part_a(&ranges, &ids), 0);
@@ -2,7 +2,11 @@
 //! Input is four lines representing many column-aligned math problems placed horizontally. The
 //! first `n-1` lines hold the operands, each problem stacked vertically with arbitrary internal
 //! spacing. A full column of spaces separates problems. The final line contains either `+` or `*`
-//! per problem to indicate whether to sum or multiply that column's operands.
+//! per problem to indicate whether to sum or multiply that column's operands. Some worksheets place
+//! the operator line first instead, above the operands. A problem may instead list one
+//! space-separated operator between each pair of consecutive operands, in which case the problem is
+//! evaluated as an expression where `*` binds tighter than `+`. Operands may optionally group their
+//! digits with a thousands separator such as `1,234`.
 //!
 //! ## Part A
 //! Split the grid into problems, parse the operands above each operator, evaluate the indicated
@@ -14,21 +18,95 @@
 //! digits, evaluate, and sum the results.
 use anyhow::{Context, Result, bail};
 
-#[derive(Debug, Clone, Copy)]
+pub const TITLE: &str = "Trash Compactor";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Operation {
     Add,
     Multiply,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+enum Operator {
+    /// A single operation applied across all operands
+    Uniform(Operation),
+    /// One operation between each pair of consecutive operands, with `*` taking precedence
+    Expression(Vec<Operation>),
+}
+
+/// Where the operator line is placed relative to the operand lines
+#[derive(Debug, Clone, Copy)]
+enum OperatorPosition {
+    Top,
+    Bottom,
+}
+
+impl OperatorPosition {
+    /// Detect the operator line as the first line when it holds nothing but operators and spaces,
+    /// otherwise as the last line.
+    fn detect(first_line: &str) -> Self {
+        let is_operator_line = first_line.chars().any(|ch| ch != ' ')
+            && first_line.chars().all(|ch| matches!(ch, '+' | '*' | ' '));
+        if is_operator_line {
+            Self::Top
+        } else {
+            Self::Bottom
+        }
+    }
+}
+
+/// Order in which the problems of a worksheet are read
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+enum ReadDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+/// How to treat problems that have an operator but no operands
+#[derive(Debug, Clone, Copy, Default)]
+enum EmptyProblems {
+    /// Fail to parse the worksheet
+    #[default]
+    Reject,
+    /// Keep the problem without operands, so it evaluates to the identity of its operation
+    Identity,
+}
+
+/// Layout variations accepted by `parse_input`
+#[derive(Debug, Clone, Copy, Default)]
+struct ParseOptions {
+    /// Thousands separator used within operands, if any
+    separator: Option<char>,
+    empty: EmptyProblems,
+}
+
+/// Placement of a problem's operands and operator within the columns it spans
+#[derive(Debug, PartialEq)]
+struct Alignment {
+    width: usize,
+    /// Offset of each horizontal operand from the first column of the problem
+    operands: Vec<usize>,
+    /// Offset of the first operator from the first column of the problem
+    operator: usize,
+}
+
+#[derive(Debug, PartialEq)]
 struct Problem {
     horizontal: Vec<usize>,
     vertical: Vec<usize>,
-    op: Operation,
+    op: Operator,
+    alignment: Alignment,
 }
 
 /// Parse the column-aligned worksheet into a list of problems with their operands and operator.
-fn parse_input(input: &str) -> Result<Vec<Problem>> {
+/// The operator line is either the last line or, when it holds only operators, the first line.
+/// When a thousands `separator` is given it's stripped from horizontal operands, while columns made
+/// up of only separators are skipped when reading vertical numbers. Placeholder problems without
+/// operands are rejected unless `empty` asks for them to evaluate to their identity. Any other
+/// problem must have an operand on every operand line.
+fn parse_input(input: &str, options: ParseOptions) -> Result<Vec<Problem>> {
+    let ParseOptions { separator, empty } = options;
     let lines: Vec<&str> = input.trim().lines().collect();
     if lines.len() < 2 {
         bail!("Expected at least two lines for operands and operators");
@@ -44,7 +122,15 @@ fn parse_input(input: &str) -> Result<Vec<Problem>> {
         })
         .collect();
 
-    let operator_row = padded.len() - 1;
+    let (operator_row, operand_rows) = match OperatorPosition::detect(lines[0]) {
+        OperatorPosition::Top => (0, 1..padded.len()),
+        OperatorPosition::Bottom => (padded.len() - 1, 0..padded.len() - 1),
+    };
+    let operands = || {
+        padded[operand_rows.clone()]
+            .iter()
+            .zip(operand_rows.clone())
+    };
     let mut problems = Vec::new();
     let mut col = 0;
     while col < width {
@@ -61,33 +147,74 @@ fn parse_input(input: &str) -> Result<Vec<Problem>> {
         let end = col;
 
         let mut horizontal = Vec::new();
+        let mut offsets = Vec::new();
         let mut vertical = Vec::new();
-        for (row, chars) in padded.iter().take(operator_row).enumerate() {
+        let mut blank_row = None;
+        for (chars, row) in operands() {
             let slice: String = chars[start..end].iter().collect();
-            let trimmed = slice.trim();
+            let trimmed: String = slice
+                .trim()
+                .chars()
+                .filter(|&ch| Some(ch) != separator)
+                .collect();
             if trimmed.is_empty() {
+                blank_row.get_or_insert(row);
                 continue;
             }
             let value = trimmed
                 .parse::<usize>()
                 .with_context(|| format!("Invalid number {trimmed:?} on line {}", row + 1))?;
             horizontal.push(value);
+            offsets.push(slice.len() - slice.trim_start().len());
         }
 
-        if horizontal.is_empty() {
+        // Every operand row has to contribute to a problem, otherwise the horizontal operands no
+        // longer line up with the rows the vertical numbers are read from
+        if let Some(row) = blank_row
+            && !horizontal.is_empty()
+        {
+            bail!(
+                "Problem spanning columns {start}-{end} is missing an operand on line {}",
+                row + 1
+            );
+        }
+
+        if horizontal.is_empty() && matches!(empty, EmptyProblems::Reject) {
             bail!("Problem spanning columns {start}-{end} has no operands");
         }
 
         let op_slice: String = padded[operator_row][start..end].iter().collect();
-        let op = match op_slice.trim() {
-            "+" => Operation::Add,
-            "*" => Operation::Multiply,
-            other => bail!("Unknown operator {other:?} at columns {start}-{end}"),
+        let mut ops = op_slice
+            .split_whitespace()
+            .map(|token| match token {
+                "+" => Ok(Operation::Add),
+                "*" => Ok(Operation::Multiply),
+                other => bail!("Unknown operator {other:?} at columns {start}-{end}"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let op = match ops.len() {
+            0 => bail!("Missing operator at columns {start}-{end}"),
+            1 => Operator::Uniform(ops.remove(0)),
+            _ => Operator::Expression(ops),
+        };
+
+        // A placeholder problem only spans its operator, so it has no vertical numbers either
+        let columns = if horizontal.is_empty() {
+            0..0
+        } else {
+            start..end
         };
+        for c in columns.rev() {
+            let is_separator_column = separator.is_some_and(|separator| {
+                operands().any(|(chars, _)| chars[c] == separator)
+                    && operands().all(|(chars, _)| chars[c] == separator || chars[c] == ' ')
+            });
+            if is_separator_column {
+                continue;
+            }
 
-        for c in (start..end).rev() {
             let mut digits = String::new();
-            for (row, chars) in padded.iter().take(operator_row).enumerate() {
+            for (chars, row) in operands() {
                 let ch = chars[c];
                 if ch.is_ascii_digit() {
                     digits.push(ch);
@@ -110,39 +237,161 @@ fn parse_input(input: &str) -> Result<Vec<Problem>> {
             horizontal,
             vertical,
             op,
+            alignment: Alignment {
+                width: end - start,
+                operands: offsets,
+                operator: op_slice.len() - op_slice.trim_start().len(),
+            },
         });
     }
 
     Ok(problems)
 }
 
-/// Evaluate a list of operands using the given operation.
-fn evaluate(op: Operation, operands: &[usize]) -> usize {
+/// Render the problems as a worksheet with the operator row at the bottom, separating problems by a
+/// single column of spaces and placing operands and operators at their parsed alignment. Thousands
+/// separators aren't rendered, so only worksheets without them survive a round trip.
+#[cfg(test)]
+fn render(problems: &[Problem]) -> String {
+    let num_rows = problems
+        .iter()
+        .map(|problem| problem.horizontal.len())
+        .max()
+        .unwrap_or(0);
+    let columns: Vec<Vec<String>> = problems
+        .iter()
+        .map(|problem| {
+            let symbol = |op: &Operation| match op {
+                Operation::Add => "+",
+                Operation::Multiply => "*",
+            };
+            let op = match &problem.op {
+                Operator::Uniform(op) => symbol(op).to_string(),
+                Operator::Expression(ops) => ops.iter().map(symbol).collect::<Vec<_>>().join(" "),
+            };
+            let Alignment {
+                width,
+                operands,
+                operator,
+            } = &problem.alignment;
+            let place =
+                |offset: usize, text: String| format!("{:width$}", " ".repeat(offset) + &text);
+            (0..num_rows)
+                .map(|row| match problem.horizontal.get(row) {
+                    Some(value) => place(operands[row], value.to_string()),
+                    None => place(0, String::new()),
+                })
+                .chain([place(*operator, op)])
+                .collect()
+        })
+        .collect();
+
+    (0..=num_rows)
+        .map(|row| {
+            let line = columns
+                .iter()
+                .map(|column| column[row].as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{line}\n")
+        })
+        .collect()
+}
+
+/// Add up values, failing instead of overflowing.
+fn checked_sum(values: impl IntoIterator<Item = usize>) -> Result<usize> {
+    values.into_iter().try_fold(0usize, |acc, value| {
+        acc.checked_add(value).context("Sum overflows")
+    })
+}
+
+/// Multiply values, failing instead of overflowing.
+fn checked_product(values: impl IntoIterator<Item = usize>) -> Result<usize> {
+    values.into_iter().try_fold(1usize, |acc, value| {
+        acc.checked_mul(value).context("Product overflows")
+    })
+}
+
+/// Evaluate a list of operands using the given operator. Expressions multiply before adding and
+/// must have exactly one operation less than there are operands. Fails if the result overflows.
+fn evaluate(op: &Operator, operands: &[usize]) -> Result<usize> {
     match op {
-        Operation::Add => operands.iter().copied().sum(),
-        Operation::Multiply => operands.iter().copied().product(),
+        Operator::Uniform(Operation::Add) => checked_sum(operands.iter().copied()),
+        Operator::Uniform(Operation::Multiply) => checked_product(operands.iter().copied()),
+        Operator::Expression(ops) => {
+            if ops.len() + 1 != operands.len() {
+                bail!(
+                    "Expression has {} operators for {} operands",
+                    ops.len(),
+                    operands.len()
+                );
+            }
+            let mut terms = vec![operands[0]];
+            for (op, &operand) in ops.iter().zip(&operands[1..]) {
+                match op {
+                    Operation::Add => terms.push(operand),
+                    Operation::Multiply => {
+                        let term = terms.last_mut().unwrap();
+                        *term = checked_product([*term, operand])?;
+                    }
+                }
+            }
+            checked_sum(terms)
+        }
     }
 }
 
-/// Sum the results of every parsed problem.
-fn part_a(problems: &[Problem]) -> usize {
-    problems
+/// Return the result of every parsed problem using horizontal operands, listed in the order the
+/// problems are read in.
+#[cfg(test)]
+fn problem_results(problems: &[Problem], direction: ReadDirection) -> Result<Vec<usize>> {
+    let mut results = problems
         .iter()
-        .map(|problem| evaluate(problem.op, &problem.horizontal))
-        .sum()
+        .map(|problem| evaluate(&problem.op, &problem.horizontal))
+        .collect::<Result<Vec<_>>>()?;
+    if let ReadDirection::RightToLeft = direction {
+        results.reverse();
+    }
+    Ok(results)
+}
+
+/// Sum the results of every parsed problem.
+fn part_a(problems: &[Problem]) -> Result<usize> {
+    checked_sum(
+        problems
+            .iter()
+            .map(|problem| evaluate(&problem.op, &problem.horizontal))
+            .collect::<Result<Vec<_>>>()?,
+    )
 }
 
 /// Sum the results of every parsed problem when numbers are read right-to-left column-wise.
-fn part_b(problems: &[Problem]) -> usize {
-    problems
-        .iter()
-        .map(|problem| evaluate(problem.op, &problem.vertical))
-        .sum()
+fn part_b(problems: &[Problem]) -> Result<usize> {
+    checked_sum(
+        problems
+            .iter()
+            .map(|problem| evaluate(&problem.op, &problem.vertical))
+            .collect::<Result<Vec<_>>>()?,
+    )
+}
+
+/// Solve both parts for a worksheet parsed with the given options.
+fn main_with(input: &str, options: ParseOptions) -> Result<(usize, Option<usize>)> {
+    let problems = parse_input(input, options)?;
+    Ok((part_a(&problems)?, Some(part_b(&problems)?)))
+}
+
+/// Solve both parts, evaluating problems without operands to the identity of their operation.
+pub fn main_keep_empty(input: &str) -> Result<(usize, Option<usize>)> {
+    let options = ParseOptions {
+        empty: EmptyProblems::Identity,
+        ..ParseOptions::default()
+    };
+    main_with(input, options)
 }
 
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let problems = parse_input(input)?;
-    Ok((part_a(&problems), Some(part_b(&problems))))
+    main_with(input, ParseOptions::default())
 }
 
 #[cfg(test)]
@@ -164,11 +413,174 @@ mod test {
 
     #[test]
     fn example_a() {
-        assert_eq!(part_a(&parse_input(EXAMPLE_INPUT).unwrap()), 4_277_556);
+        assert_eq!(
+            part_a(&parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap()).unwrap(),
+            4_277_556
+        );
     }
 
     #[test]
     fn example_b() {
-        assert_eq!(part_b(&parse_input(EXAMPLE_INPUT).unwrap()), 3_263_827);
+        assert_eq!(
+            part_b(&parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap()).unwrap(),
+            3_263_827
+        );
+    }
+
+    #[test]
+    fn results_per_problem() {
+        let problems = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        assert_eq!(
+            problem_results(&problems, ReadDirection::LeftToRight).unwrap(),
+            vec![33_210, 490, 4_243_455, 401]
+        );
+        assert_eq!(
+            problem_results(&problems, ReadDirection::RightToLeft).unwrap(),
+            vec![401, 4_243_455, 490, 33_210]
+        );
+    }
+
+    #[test]
+    fn missing_operand() {
+        let input = "123 328\n 45    \n  6 98 \n*   +  ";
+        let err = parse_input(input, ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Problem spanning columns 4-7 is missing an operand on line 2"
+        );
+
+        // A row of a single problem can't be blank either
+        let input = "328\n   \n 98\n+  ";
+        let err = parse_input(input, ParseOptions::default()).unwrap_err();
+        assert!(
+            err.to_string().ends_with("missing an operand on line 2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn operators_on_top() {
+        let input = dedent!(
+            r#"
+                *   +   *   +  
+                123 328  51 64 
+                 45 64  387 23 
+                  6 98  215 314
+            "#
+        );
+        let problems = parse_input(input, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(&problems).unwrap(), 4_277_556);
+        assert_eq!(part_b(&problems).unwrap(), 3_263_827);
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let problems = parse_input(EXAMPLE_INPUT, ParseOptions::default()).unwrap();
+        let rendered = render(&problems);
+        assert_eq!(
+            rendered,
+            "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  \n"
+        );
+        let reparsed = parse_input(&rendered, ParseOptions::default()).unwrap();
+        assert_eq!(reparsed, problems);
+
+        let input = dedent!(
+            r#"
+                123  4 51
+                 45 15 87
+                  6  6  5
+                + * +  *
+            "#
+        );
+        let problems = parse_input(input, ParseOptions::default()).unwrap();
+        let reparsed = parse_input(&render(&problems), ParseOptions::default()).unwrap();
+        assert_eq!(reparsed, problems);
+    }
+
+    #[test]
+    fn overflow() {
+        let input = dedent!(
+            r#"
+                99999999999
+                99999999999
+                *
+            "#
+        );
+        let problems = parse_input(input, ParseOptions::default()).unwrap();
+        assert!(part_a(&problems).is_err());
+    }
+
+    #[test]
+    fn mixed_operators() {
+        let input = dedent!(
+            r#"
+                123 4
+                 45 5
+                  6 6
+                + * +
+            "#
+        );
+        let problems = parse_input(input, ParseOptions::default()).unwrap();
+        assert_eq!(part_a(&problems).unwrap(), 123 + 45 * 6 + 15);
+        assert_eq!(part_b(&problems).unwrap(), 356 + 24 + 456);
+
+        let problems = parse_input("123\n+ *", ParseOptions::default()).unwrap();
+        assert!(part_a(&problems).is_err());
+    }
+
+    #[test]
+    fn thousands_separators() {
+        let input = dedent!(
+            r#"
+                1,234 10
+                  567 20
+                +     *
+            "#
+        );
+        assert!(parse_input(input, ParseOptions::default()).is_err());
+
+        let separated = ParseOptions {
+            separator: Some(','),
+            ..ParseOptions::default()
+        };
+        let problems = parse_input(input, separated).unwrap();
+        assert_eq!(problems[0].horizontal, vec![1234, 567]);
+        assert_eq!(part_a(&problems).unwrap(), 1234 + 567 + 10 * 20);
+        assert_eq!(part_b(&problems).unwrap(), 47 + 36 + 25 + 1);
+
+        let err = parse_input("1,2\n345\n+", separated).unwrap_err();
+        assert!(err.to_string().contains("Invalid character ','"), "{err}");
+    }
+
+    #[test]
+    fn empty_problems() {
+        let input = "12     3\n 4     5\n+  * + *";
+        let err = parse_input(input, ParseOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("has no operands"), "{err}");
+
+        let problems = parse_input(
+            input,
+            ParseOptions {
+                empty: EmptyProblems::Identity,
+                ..ParseOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(problems.len(), 4);
+        assert!(problems[1].horizontal.is_empty() && problems[1].vertical.is_empty());
+        assert_eq!(
+            evaluate(&problems[1].op, &problems[1].horizontal).unwrap(),
+            1
+        );
+        assert_eq!(
+            evaluate(&problems[2].op, &problems[2].horizontal).unwrap(),
+            0
+        );
+        assert_eq!(part_a(&problems).unwrap(), 16 + 1 + 15);
+        assert_eq!(part_b(&problems).unwrap(), 24 + 1 + 1 + 35);
+        assert_eq!(
+            main_keep_empty(input).unwrap(),
+            (16 + 1 + 15, Some(24 + 1 + 1 + 35))
+        );
     }
 }
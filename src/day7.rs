@@ -1,6 +1,8 @@
 //! # Day 7: Laboratories
 //! Input is a rectangular map of `.` empty space, `^` splitters, and exactly one `S` start
-//! location. A tachyon beam begins directly below `S` and always moves downward.
+//! location. A tachyon beam begins directly below `S` and always moves downward. Splitters may also
+//! be one-sided: `<` only sends the beam to the left and `>` only to the right. Some manifolds
+//! have several start locations, each launching its own beam.
 //!
 //! ## Part A
 //! A beam passes through empty space unchanged. When it reaches a splitter, that beam stops and
@@ -13,31 +15,97 @@
 //! paths, creating a separate timeline for each choice. Timelines that later share the same path
 //! still remain distinct. Count how many timelines exist after the particle finishes traversing
 //! the manifold.
-use anyhow::{Context, Result, bail};
+use anyhow::{Result, bail};
 use std::cmp::Reverse;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+pub const TITLE: &str = "Laboratories";
+
+/// A cell can be entered by beams split off from a splitter on either side of it.
+const MAX_VISITS_PER_CELL: usize = 2;
+
+/// Characters used by `render_heatmap` for splitters reached by increasingly many timelines.
+const HEAT_LEVELS: [char; 4] = ['.', ':', '+', '#'];
+
+/// Largest part of the manifold that `render_heatmap` draws, as columns and rows.
+const MAX_RENDER_SIZE: (usize, usize) = (200, 200);
 
 type Cell = (usize, usize);
 
+#[derive(Debug, Clone, Copy)]
+enum Splitter {
+    /// `^` sends beams both left and right
+    Both,
+    /// `<` only sends beams to the left
+    Left,
+    /// `>` only sends beams to the right
+    Right,
+}
+
+impl Splitter {
+    /// Return the columns a beam hitting this splitter at `x` continues in. Beams that would leave
+    /// a manifold of the given width are returned as the side they exit through instead.
+    fn outputs(self, x: usize, width: usize) -> Vec<Result<usize, Exit>> {
+        let left = x.checked_sub(1).ok_or(Exit::Left);
+        let right = x.checked_add(1).filter(|&nx| nx < width).ok_or(Exit::Right);
+        match self {
+            Splitter::Both => vec![left, right],
+            Splitter::Left => vec![left],
+            Splitter::Right => vec![right],
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Manifold {
-    splitters: HashSet<Cell>,
-    start: Cell,
+    splitters: HashMap<Cell, Splitter>,
+    starts: Vec<Cell>,
     height: usize,
     width: usize,
 }
 
 impl Manifold {
-    fn next_splitter(&self, x: usize, y: usize) -> Option<Cell> {
-        (y..self.height).find_map(|ny| self.splitters.contains(&(x, ny)).then_some((x, ny)))
+    /// Build a table indexed by `[y][x]` holding the row of the first splitter in column `x` at or
+    /// below row `y`.
+    fn next_splitter_table(&self) -> Vec<Vec<Option<usize>>> {
+        let mut table = vec![vec![None; self.width]; self.height + 1];
+        for y in (0..self.height).rev() {
+            table[y] = (0..self.width)
+                .map(|x| {
+                    if self.splitters.contains_key(&(x, y)) {
+                        Some(y)
+                    } else {
+                        table[y + 1][x]
+                    }
+                })
+                .collect();
+        }
+        table
+    }
+
+    /// Fail once a traversal took more steps than beams moving downward could ever need, which
+    /// means they can never leave the manifold, or more steps than the optional `max_steps` budget.
+    fn check_step_limit(&self, steps: usize, max_steps: Option<usize>) -> Result<()> {
+        let limit = MAX_VISITS_PER_CELL * self.width * self.height + 1;
+        if steps > limit {
+            bail!("Traversal exceeded {limit} steps; beams may be looping");
+        }
+        if let Some(max_steps) = max_steps
+            && steps > max_steps
+        {
+            bail!("Traversal exceeded step budget of {max_steps}");
+        }
+        Ok(())
     }
 }
-/// Parse the manifold into splitter coordinates and locate the start cell.
-fn parse_input(input: &str) -> Result<Manifold> {
+
+/// Parse the manifold into splitter coordinates and locate the start cell. A second start cell is
+/// rejected unless `multi_start` is set, in which case every start cell launches a beam.
+fn parse_input(input: &str, multi_start: bool) -> Result<Manifold> {
     let lines: Vec<&str> = input.trim().lines().collect();
     let mut width = 0;
-    let mut splitters = HashSet::new();
-    let mut start = None;
+    let mut splitters = HashMap::new();
+    let mut starts = Vec::new();
 
     for (y, line) in lines.iter().enumerate() {
         width = width.max(line.len());
@@ -45,99 +113,309 @@ fn parse_input(input: &str) -> Result<Manifold> {
             match ch {
                 '.' => {}
                 '^' => {
-                    splitters.insert((x, y));
+                    splitters.insert((x, y), Splitter::Both);
+                }
+                '<' => {
+                    splitters.insert((x, y), Splitter::Left);
+                }
+                '>' => {
+                    splitters.insert((x, y), Splitter::Right);
                 }
                 'S' => {
-                    if start.replace((x, y)).is_some() {
+                    if !multi_start && !starts.is_empty() {
                         bail!("Second start position found on line {}", y + 1);
                     }
+                    starts.push((x, y));
                 }
                 other => bail!("Invalid character {other:?} on line {}", y + 1),
             }
         }
     }
 
+    if starts.is_empty() {
+        bail!("Missing start position S");
+    }
+
     Ok(Manifold {
         splitters,
-        start: start.context("Missing start position S")?,
+        starts,
         height: lines.len(),
         width,
     })
 }
 
-/// Count how often beams are split until every beam exits the manifold.
-fn part_a(manifold: &Manifold) -> usize {
-    let mut queue = Vec::new();
-    let mut visited = HashSet::new();
-    let mut splits = 0;
+/// Place where timelines leave the manifold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Exit {
+    /// Out of the bottom edge in the given column
+    Bottom(usize),
+    /// Past the left edge next to a splitter
+    Left,
+    /// Past the right edge next to a splitter
+    Right,
+}
 
-    queue.push((manifold.start.0, manifold.start.1 + 1));
-    while let Some((x, y)) = queue.pop() {
-        if let Some((hit_x, hit_y)) = manifold.next_splitter(x, y)
-            && visited.insert((hit_x, hit_y))
-        {
-            splits += 1;
-            if hit_x > 0 {
-                queue.push((hit_x - 1, hit_y));
-            }
-            if hit_x + 1 < manifold.width {
-                queue.push((hit_x + 1, hit_y));
+/// Propagate timeline counts through the splitters row by row, combining counts with `add`.
+/// Timelines reaching a splitter are reported to `visit` and timelines leaving the manifold to
+/// `exit`. Fail after visiting more than `max_steps` splitters when given. Return how many
+/// splitters were reached.
+fn propagate(
+    manifold: &Manifold,
+    max_steps: Option<usize>,
+    add: impl Fn(usize, usize) -> usize,
+    mut visit: impl FnMut(Cell, usize),
+    mut exit: impl FnMut(Exit, usize),
+) -> Result<usize> {
+    let next_splitter = manifold.next_splitter_table();
+    let mut counts: HashMap<Cell, usize> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    let mut reached = HashSet::new();
+    let mut steps = 0;
+
+    // Beams waiting to travel down column `x` from row `y`, carrying `count` timelines
+    let mut pending: Vec<_> = manifold
+        .starts
+        .iter()
+        .map(|&(x, y)| (Ok(x), y + 1, add(0, 1)))
+        .collect();
+    loop {
+        for (x, y, count) in pending.drain(..) {
+            let x = match x {
+                Ok(x) => x,
+                Err(side) => {
+                    exit(side, count);
+                    continue;
+                }
+            };
+            match next_splitter[y][x] {
+                Some(sy) => {
+                    let entry = counts.entry((x, sy)).or_insert_with(|| {
+                        heap.push(Reverse((sy, x)));
+                        0
+                    });
+                    *entry = add(*entry, count);
+                }
+                None => exit(Exit::Bottom(x), count),
             }
         }
+
+        let Some(Reverse((y, x))) = heap.pop() else {
+            break;
+        };
+        steps += 1;
+        manifold.check_step_limit(steps, max_steps)?;
+        let Some(count) = counts.remove(&(x, y)) else {
+            continue;
+        };
+        reached.insert((x, y));
+        visit((x, y), count);
+        pending.extend(
+            manifold.splitters[&(x, y)]
+                .outputs(x, manifold.width)
+                .into_iter()
+                .map(|next_x| (next_x, y, count)),
+        );
     }
-    debug_assert!(splits <= manifold.splitters.len());
-    splits
+
+    debug_assert!(reached.len() <= manifold.splitters.len());
+    Ok(reached.len())
 }
 
-/// Count how many distinct timelines exist when the particle splits at every encountered splitter.
-fn part_b(manifold: &Manifold) -> usize {
-    let mut counts: HashMap<Cell, usize> = HashMap::new();
-    let mut heap = std::collections::BinaryHeap::new();
-    let mut timelines = 0usize;
+/// Count how many splitters were reached along with the number of timelines leaving the manifold,
+/// combining counts with `add` and visiting at most `max_steps` splitters when given.
+#[cfg(test)]
+fn count_timelines(
+    manifold: &Manifold,
+    max_steps: Option<usize>,
+    add: impl Fn(usize, usize) -> usize,
+) -> Result<(usize, usize)> {
+    let mut timelines = 0;
+    let splits = propagate(
+        manifold,
+        max_steps,
+        &add,
+        |_, _| {},
+        |_, count| timelines = add(timelines, count),
+    )?;
+    Ok((splits, timelines))
+}
 
-    let start_y = manifold.start.1 + 1;
-    if let Some(start_splitter) = manifold.next_splitter(manifold.start.0, start_y) {
-        counts.insert(start_splitter, 1);
-        heap.push(Reverse((start_splitter.1, start_splitter.0)));
-    } else {
-        // We never hit any splitter
-        return 1;
+/// Count how many timelines leave the manifold through each exit.
+#[cfg(test)]
+fn exit_distribution(manifold: &Manifold) -> Result<HashMap<Exit, usize>> {
+    let mut exits = HashMap::new();
+    propagate(
+        manifold,
+        None,
+        |a, b| a + b,
+        |_, _| {},
+        |exit, count| *exits.entry(exit).or_insert(0) += count,
+    )?;
+    Ok(exits)
+}
+
+/// Count how many part A beams leave the manifold past the left edge, past the right edge and out
+/// of the bottom edge. Beams merge like in part A, so every column of the bottom edge counts once.
+#[cfg(test)]
+fn exit_sides(manifold: &Manifold) -> Result<(usize, usize, usize)> {
+    let (mut left, mut right) = (0, 0);
+    let mut bottom = HashSet::new();
+    propagate(
+        manifold,
+        None,
+        |a, b| (a + b).min(1),
+        |_, _| {},
+        |exit, _| match exit {
+            Exit::Left => left += 1,
+            Exit::Right => right += 1,
+            Exit::Bottom(x) => {
+                bottom.insert(x);
+            }
+        },
+    )?;
+    Ok((left, right, bottom.len()))
+}
+
+/// Count how many timelines reach each splitter. Splitters no beam reaches are left out.
+fn splitter_counts(manifold: &Manifold) -> Result<HashMap<Cell, usize>> {
+    let mut counts = HashMap::new();
+    propagate(
+        manifold,
+        None,
+        |a, b| a + b,
+        |cell, count| {
+            counts.insert(cell, count);
+        },
+        |_, _| {},
+    )?;
+    Ok(counts)
+}
+
+/// Return the heat level of a splitter reached by `count` timelines when the busiest one is reached
+/// by `max` timelines. Counts grow exponentially with depth, so levels are spread by bit length.
+fn heat_level(count: usize, max: usize) -> char {
+    let top = HEAT_LEVELS.len() - 1;
+    let (bits, max_bits) = (count.ilog2() as usize, max.ilog2() as usize);
+    let level = (bits * top + max_bits / 2)
+        .checked_div(max_bits)
+        .unwrap_or(top);
+    HEAT_LEVELS[level.min(top)]
+}
+
+/// Render the manifold with every reached splitter replaced by how many timelines passed through
+/// it, from `.` for the fewest to `#` for the most. Empty space is blank and unreached splitters
+/// keep their symbol. Large manifolds are cropped to `MAX_RENDER_SIZE`.
+fn render_heatmap(manifold: &Manifold) -> Result<String> {
+    let counts = splitter_counts(manifold)?;
+    let max = counts.values().copied().max().unwrap_or(1);
+    let (max_width, max_height) = MAX_RENDER_SIZE;
+    let (width, height) = (
+        manifold.width.min(max_width),
+        manifold.height.min(max_height),
+    );
+
+    let mut rendered = String::new();
+    for y in 0..height {
+        let line: String = (0..width)
+            .map(
+                |x| match (counts.get(&(x, y)), manifold.splitters.get(&(x, y))) {
+                    (Some(&count), _) => heat_level(count, max),
+                    (None, Some(Splitter::Both)) => '^',
+                    (None, Some(Splitter::Left)) => '<',
+                    (None, Some(Splitter::Right)) => '>',
+                    (None, None) if manifold.starts.contains(&(x, y)) => 'S',
+                    (None, None) => ' ',
+                },
+            )
+            .collect();
+        rendered.push_str(line.trim_end());
+        rendered.push('\n');
+    }
+    if (width, height) != (manifold.width, manifold.height) {
+        rendered.push_str(&format!(
+            "(cropped to {width}x{height} of {}x{})\n",
+            manifold.width, manifold.height
+        ));
     }
+    Ok(rendered)
+}
 
-    while let Some(Reverse((y, x))) = heap.pop() {
-        let count = counts.remove(&(x, y)).unwrap_or(0);
-        if count == 0 {
-            continue;
-        }
+/// Parse the manifold and render a heat map of how many timelines pass through each splitter.
+pub fn render(input: &str) -> Result<String> {
+    render_heatmap(&parse_input(input, false)?)
+}
 
-        for next_x in [
-            x.checked_sub(1),
-            x.checked_add(1).filter(|&nx| nx < manifold.width),
-        ] {
-            let Some(next_x) = next_x else {
-                timelines += count;
-                continue;
-            };
+/// Splitters reached and beams leaving the manifold during a traversal
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Traversal {
+    splits: usize,
+    /// Every timeline leaving the manifold is a separate beam unless equivalent beams merge
+    exits: usize,
+}
 
-            if let Some((sx, sy)) = manifold.next_splitter(next_x, y) {
-                let entry = counts.entry((sx, sy)).or_insert(0);
-                if *entry == 0 {
-                    heap.push(Reverse((sy, sx)));
-                }
-                *entry += count;
-                continue;
+/// Walk the beams through the manifold. With `merge` equivalent beams merge like in part A,
+/// otherwise every timeline stays distinct like in part B. Either way the same splitters are
+/// reached, only the number of beams leaving the manifold differs.
+fn traverse(manifold: &Manifold, merge: bool) -> Result<Traversal> {
+    traverse_bounded(manifold, merge, None)
+}
+
+/// Like `traverse`, but fail instead of visiting more than `max_steps` splitters when given.
+fn traverse_bounded(
+    manifold: &Manifold,
+    merge: bool,
+    max_steps: Option<usize>,
+) -> Result<Traversal> {
+    let add: fn(usize, usize) -> usize = if merge {
+        |a, b| (a + b).min(1)
+    } else {
+        |a, b| a + b
+    };
+    let mut exits = 0;
+    let mut bottom = HashSet::new();
+    let splits = propagate(
+        manifold,
+        max_steps,
+        add,
+        |_, _| {},
+        |exit, count| match exit {
+            // Merged beams reaching the bottom in the same column are a single beam
+            Exit::Bottom(x) if merge => {
+                bottom.insert(x);
             }
+            _ => exits += count,
+        },
+    )?;
+    Ok(Traversal {
+        splits,
+        exits: exits + bottom.len(),
+    })
+}
 
-            timelines += count;
-        }
-    }
+/// Count how often beams are split along with how many distinct timelines exist when the particle
+/// splits at every encountered splitter, in a single pass over the splitters.
+fn solve(manifold: &Manifold) -> Result<(usize, usize)> {
+    let Traversal { splits, exits } = traverse(manifold, false)?;
+    Ok((splits, exits))
+}
 
-    timelines
+/// Count timelines modulo `modulus` as a cheap cross-check of `solve` against overflow.
+#[cfg(test)]
+fn part_b_mod(manifold: &Manifold, modulus: u64) -> Result<u64> {
+    if modulus == 0 {
+        bail!("Modulus must be positive");
+    }
+    // Widen the sum so moduli close to `u64::MAX` can't overflow
+    let (_, timelines) = count_timelines(manifold, None, |a, b| {
+        ((a as u128 + b as u128) % modulus as u128) as usize
+    })?;
+    Ok(timelines as u64)
 }
 
 pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let manifold = parse_input(input)?;
-    Ok((part_a(&manifold), Some(part_b(&manifold))))
+    let manifold = parse_input(input, false)?;
+    let (splits, timelines) = solve(&manifold)?;
+    Ok((splits, Some(timelines)))
 }
 
 #[cfg(test)]
@@ -171,13 +449,184 @@ mod test {
 
     #[test]
     fn example_a() {
-        let manifold = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_a(&manifold), 21);
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        assert_eq!(solve(&manifold).unwrap().0, 21);
     }
 
     #[test]
     fn example_b() {
-        let manifold = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_b(&manifold), 40);
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        assert_eq!(solve(&manifold).unwrap().1, 40);
+    }
+
+    #[test]
+    fn merged_traversal() {
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        let merged = traverse(&manifold, true).unwrap();
+        let distinct = traverse(&manifold, false).unwrap();
+        assert_eq!(merged.splits, 21);
+        assert_eq!(distinct.exits, 40);
+        // Merged beams reach the same splitters but leave through one beam per bottom column
+        assert_eq!(distinct.splits, merged.splits);
+        assert_eq!(merged.exits, 9);
+        let (left, right, bottom) = exit_sides(&manifold).unwrap();
+        assert_eq!(merged.exits, left + right + bottom);
+
+        let input = std::fs::read_to_string("data/day7.txt").unwrap();
+        let manifold = parse_input(&input, false).unwrap();
+        assert_eq!(
+            (
+                traverse(&manifold, true).unwrap().splits,
+                traverse(&manifold, false).unwrap().exits
+            ),
+            solve(&manifold).unwrap()
+        );
+    }
+
+    #[test]
+    fn one_sided_splitters() {
+        let input = dedent!(
+            r#"
+                ..S..
+                .....
+                ..<..
+                .....
+                .^...
+                .....
+                ..>..
+            "#
+        );
+        let manifold = parse_input(input, false).unwrap();
+        assert_eq!(solve(&manifold).unwrap(), (3, 2));
+
+        let manifold = parse_input("S\n.\n>", false).unwrap();
+        assert_eq!(solve(&manifold).unwrap(), (1, 1));
+    }
+
+    #[test]
+    fn multiple_starts() {
+        let input = dedent!(
+            r#"
+                .S.S.
+                .....
+                .^.^.
+                .....
+                ..^..
+            "#
+        );
+        assert!(parse_input(input, false).is_err());
+
+        // Both starts feed the bottom splitter, which is only counted once
+        let manifold = parse_input(input, true).unwrap();
+        assert_eq!(solve(&manifold).unwrap(), (3, 6));
+
+        let single = parse_input(".S.\n...\n.^.", true).unwrap();
+        assert_eq!(solve(&single).unwrap(), (1, 2));
+
+        let shared = parse_input("S.S\n...\n.^.", true).unwrap();
+        assert_eq!(solve(&shared).unwrap(), (0, 2));
+    }
+
+    #[test]
+    fn step_budget() {
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        let traversal = Traversal {
+            splits: 21,
+            exits: 40,
+        };
+        assert_eq!(
+            traverse_bounded(&manifold, false, Some(21)).unwrap(),
+            traversal
+        );
+        let err = traverse_bounded(&manifold, false, Some(5)).unwrap_err();
+        assert_eq!(err.to_string(), "Traversal exceeded step budget of 5");
+        assert_eq!(traverse_bounded(&manifold, false, None).unwrap(), traversal);
+    }
+
+    #[test]
+    fn exits() {
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        let exits = exit_distribution(&manifold).unwrap();
+        assert_eq!(exits.values().sum::<usize>(), 40);
+        let bottom: Vec<_> = (0..manifold.width)
+            .map(|x| exits.get(&Exit::Bottom(x)).copied().unwrap_or(0))
+            .collect();
+        assert_eq!(bottom, vec![1, 0, 2, 0, 10, 0, 11, 0, 11, 0, 2, 1, 1, 0, 1]);
+        assert_eq!(exits.get(&Exit::Left), None);
+        assert_eq!(exits.get(&Exit::Right), None);
+
+        let manifold = parse_input("S.\n..\n^.", false).unwrap();
+        let exits = exit_distribution(&manifold).unwrap();
+        assert_eq!(
+            exits,
+            HashMap::from([(Exit::Left, 1), (Exit::Bottom(1), 1)])
+        );
+    }
+
+    #[test]
+    fn exit_edges() {
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        assert_eq!(exit_sides(&manifold).unwrap(), (0, 0, 9));
+
+        let input = dedent!(
+            r#"
+                ..S..
+                .....
+                ..^..
+                .....
+                .^.^.
+                .....
+                ^...^
+                .....
+                ..<..
+            "#
+        );
+        let manifold = parse_input(input, false).unwrap();
+        assert_eq!(exit_sides(&manifold).unwrap(), (1, 1, 2));
+
+        // Beams turned into the same column merge before reaching the bottom
+        let manifold = parse_input(".S.\n...\n.^.\n...\n>.<\n...", false).unwrap();
+        assert_eq!(exit_sides(&manifold).unwrap(), (0, 0, 1));
+    }
+
+    #[test]
+    fn heatmap() {
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        let rendered = render_heatmap(&manifold).unwrap();
+        let rows: Vec<&str> = rendered.lines().collect();
+        assert_eq!(rows.len(), manifold.height);
+        assert!(rows.iter().skip(1).step_by(2).all(|row| row.is_empty()));
+        assert_eq!(
+            rows.into_iter().step_by(2).collect::<Vec<_>>(),
+            vec![
+                "       S",
+                "       .",
+                "      . .",
+                "     . + .",
+                "    . +   .",
+                "   . #   . .",
+                "  .   #     .",
+                " . . # # ^   .",
+            ]
+        );
+        assert_eq!(solve(&manifold).unwrap(), (21, 40));
+
+        let wide = format!("S{}\n{}\n^", ".".repeat(300), ".".repeat(301));
+        let rendered = render_heatmap(&parse_input(&wide, false).unwrap()).unwrap();
+        assert_eq!(rendered, "S\n\n#\n(cropped to 200x3 of 301x3)\n");
+    }
+
+    #[test]
+    fn timelines_modulo_prime() {
+        const PRIME: u64 = 1_000_000_007;
+
+        let manifold = parse_input(EXAMPLE_INPUT, false).unwrap();
+        assert_eq!(part_b_mod(&manifold, 7).unwrap(), 40 % 7);
+        assert_eq!(part_b_mod(&manifold, PRIME).unwrap(), 40);
+
+        let input = std::fs::read_to_string("data/day7.txt").unwrap();
+        let manifold = parse_input(&input, false).unwrap();
+        let timelines = solve(&manifold).unwrap().1 as u64;
+        assert_eq!(part_b_mod(&manifold, PRIME).unwrap(), timelines % PRIME);
     }
 }
@@ -1,5 +1,7 @@
 //! # Day 8: Playground
-//! Input is a list of junction box coordinates, one per line, formatted as `x,y,z` integers.
+//! Input is a list of junction box coordinates, one per line, formatted as `x,y,z` integers. The Z
+//! coordinate may be omitted (`x,y`), in which case it's treated as 0. If any coordinate contains a
+//! `.`, all coordinates are read as decimals instead.
 //!
 //! ## Part A
 //! Compute straight-line distances between every pair of boxes and order the unique pairs by
@@ -11,23 +13,123 @@
 //! ## Part B
 //! Keep connecting boxes in that same order until all boxes belong to a single circuit. Return the
 //! product of the X coordinates of the final connection that merges the circuits into one.
-use anyhow::{Context, Result, bail};
-use std::cmp::Reverse;
+use anyhow::{Result, bail};
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
+use std::fmt;
+#[cfg(test)]
+use std::io::BufRead;
+use std::ops::Mul;
+use std::str::FromStr;
+
+use crate::parse;
+
+pub const TITLE: &str = "Playground";
 
 const CONNECTIONS: usize = 1000;
+const LARGEST_CIRCUITS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point<C> {
+    x: C,
+    y: C,
+    z: C,
+}
+
+/// Coordinate value of a junction box. Integers keep distances exact while decimals allow
+/// fractional positions.
+trait Coordinate:
+    Copy
+    + Default
+    + fmt::Debug
+    + Mul<Output = Self>
+    + FromStr<Err: std::error::Error + Send + Sync + 'static>
+{
+    /// Totally ordered squared distance between two boxes.
+    type Distance: Ord + Copy + fmt::Debug;
+
+    fn squared_distance(a: &Point<Self>, b: &Point<Self>) -> Self::Distance;
+
+    /// Check whether the coordinate can take part in distance calculations.
+    fn is_valid(self) -> bool;
+}
+
+impl Coordinate for usize {
+    type Distance = u128;
+
+    fn squared_distance(a: &Point<Self>, b: &Point<Self>) -> u128 {
+        let dx = a.x.abs_diff(b.x) as u128;
+        let dy = a.y.abs_diff(b.y) as u128;
+        let dz = a.z.abs_diff(b.z) as u128;
+        dx * dx + dy * dy + dz * dz
+    }
+
+    fn is_valid(self) -> bool {
+        true
+    }
+}
+
+/// Squared distance between decimal coordinates. Coordinates are finite so the distance is never
+/// NaN, which makes the ordering total.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DecimalDistance(f64);
+
+impl Eq for DecimalDistance {}
+
+impl PartialOrd for DecimalDistance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DecimalDistance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Coordinate for f64 {
+    type Distance = DecimalDistance;
+
+    fn squared_distance(a: &Point<Self>, b: &Point<Self>) -> DecimalDistance {
+        let (dx, dy, dz) = (a.x - b.x, a.y - b.y, a.z - b.z);
+        DecimalDistance(dx * dx + dy * dy + dz * dz)
+    }
+
+    fn is_valid(self) -> bool {
+        self.is_finite()
+    }
+}
+
+/// Junction boxes with either exact integer or decimal coordinates.
+#[derive(Debug, PartialEq)]
+pub enum Boxes {
+    Integer(Vec<Point<usize>>),
+    Decimal(Vec<Point<f64>>),
+}
+
+/// Product of the X coordinates of two junction boxes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Product {
+    Integer(usize),
+    Decimal(f64),
+}
 
-#[derive(Debug, Clone, Copy)]
-struct Point {
-    x: usize,
-    y: usize,
-    z: usize,
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Product::Integer(value) => write!(f, "{value}"),
+            Product::Decimal(value) => write!(f, "{value}"),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct UnionFind {
     parent: Vec<usize>,
     size: Vec<usize>,
+    /// Size of the largest set, kept up to date by `union`
+    max_size: usize,
 }
 
 impl UnionFind {
@@ -35,6 +137,7 @@ impl UnionFind {
         Self {
             parent: (0..len).collect(),
             size: vec![1; len],
+            max_size: len.min(1),
         }
     }
 
@@ -47,19 +150,22 @@ impl UnionFind {
         root
     }
 
-    fn union(&mut self, a: usize, b: usize) {
+    /// Merge the sets containing `a` and `b`. Return `false` if they already were the same set.
+    fn union(&mut self, a: usize, b: usize) -> bool {
         let ra = self.find(a);
         let rb = self.find(b);
         if ra == rb {
-            return;
+            return false;
         }
-        if self.size[ra] < self.size[rb] {
-            self.parent[ra] = rb;
-            self.size[rb] += self.size[ra];
+        let (child, root) = if self.size[ra] < self.size[rb] {
+            (ra, rb)
         } else {
-            self.parent[rb] = ra;
-            self.size[ra] += self.size[rb];
-        }
+            (rb, ra)
+        };
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        self.max_size = self.max_size.max(self.size[root]);
+        true
     }
 
     fn component_sizes(&mut self) -> Vec<usize> {
@@ -72,75 +178,198 @@ impl UnionFind {
     }
 }
 
-/// Parse a list of strict `x,y,z` coordinate triples into points.
-fn parse_input(input: &str) -> Result<Vec<Point>> {
+/// Parse a strict `x,y,z` coordinate triple into a point. A missing Z coordinate defaults to 0 to
+/// allow 2D inputs.
+fn parse_point<C: Coordinate>(line: &str, line_no: usize) -> Result<Point<C>> {
+    let [x, y, z] = if line.matches(',').count() == 1 {
+        let [x, y] = parse::fixed_tuple(line, line_no)?;
+        [x, y, C::default()]
+    } else {
+        parse::fixed_tuple(line, line_no)?
+    };
+    if ![x, y, z].into_iter().all(C::is_valid) {
+        bail!("Coordinates must be finite on line {}", line_no);
+    }
+    Ok(Point { x, y, z })
+}
+
+/// Parse a list of coordinate triples into points, one per line.
+fn parse_points<C: Coordinate>(input: &str) -> Result<Vec<Point<C>>> {
     input
         .trim()
         .lines()
         .enumerate()
-        .map(|(idx, line)| {
-            let line_no = idx + 1;
-            let mut parts = line.split(',');
-            let x = parts
-                .next()
-                .context("Missing X coordinate")?
-                .parse()
-                .with_context(|| format!("Invalid X value on line {}", line_no))?;
-            let y = parts
-                .next()
-                .context("Missing Y coordinate")?
-                .parse()
-                .with_context(|| format!("Invalid Y value on line {}", line_no))?;
-            let z = parts
-                .next()
-                .context("Missing Z coordinate")?
-                .parse()
-                .with_context(|| format!("Invalid Z value on line {}", line_no))?;
-
-            if parts.next().is_some() {
-                bail!("Too many comma-separated values on line {}", line_no);
-            }
-
-            Ok(Point { x, y, z })
-        })
+        .map(|(idx, line)| parse_point(line, idx + 1))
         .collect()
 }
 
-fn squared_distance(a: &Point, b: &Point) -> u128 {
-    let dx = a.x.abs_diff(b.x) as u128;
-    let dy = a.y.abs_diff(b.y) as u128;
-    let dz = a.z.abs_diff(b.z) as u128;
-    dx * dx + dy * dy + dz * dz
+/// Parse junction boxes line by line from a reader without holding the whole input in memory.
+/// Coordinates are read as integers until the first line containing a `.`, at which point every box
+/// read so far is converted to decimals.
+#[cfg(test)]
+pub fn parse_reader(reader: impl BufRead) -> Result<Boxes> {
+    use anyhow::Context;
+
+    let mut boxes = Boxes::Integer(Vec::new());
+    for (idx, line) in reader.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.with_context(|| format!("Failed to read line {}", line_no))?;
+        if let Boxes::Integer(points) = &boxes
+            && line.contains('.')
+        {
+            let points = points
+                .iter()
+                .map(|point| Point {
+                    x: point.x as f64,
+                    y: point.y as f64,
+                    z: point.z as f64,
+                })
+                .collect();
+            boxes = Boxes::Decimal(points);
+        }
+        match &mut boxes {
+            Boxes::Integer(points) => points.push(parse_point(&line, line_no)?),
+            Boxes::Decimal(points) => points.push(parse_point(&line, line_no)?),
+        }
+    }
+    Ok(boxes)
 }
 
-fn sorted_edges(points: &[Point]) -> Vec<(u128, usize, usize)> {
+/// Parse junction boxes, reading coordinates as decimals if any of them contains a `.`.
+pub fn parse_input(input: &str) -> Result<Boxes> {
+    if input.contains('.') {
+        Ok(Boxes::Decimal(parse_points(input)?))
+    } else {
+        Ok(Boxes::Integer(parse_points(input)?))
+    }
+}
+
+fn squared_distance<C: Coordinate>(a: &Point<C>, b: &Point<C>) -> C::Distance {
+    C::squared_distance(a, b)
+}
+
+fn all_edges<C: Coordinate>(points: &[Point<C>]) -> Vec<(C::Distance, usize, usize)> {
     let mut edges = Vec::new();
     for i in 0..points.len() {
         for j in (i + 1)..points.len() {
             edges.push((squared_distance(&points[i], &points[j]), i, j));
         }
     }
+    edges
+}
 
+/// Return every pair of boxes ordered by increasing distance. Pairs at equal distance keep the
+/// order `all_edges` generates them in, which is by the input position of the first and then the
+/// second box.
+fn sorted_edges<C: Coordinate>(points: &[Point<C>]) -> Vec<(C::Distance, usize, usize)> {
+    let mut edges = all_edges(points);
     edges.sort_by_key(|&(dist, i, j)| (dist, i, j));
     edges
 }
 
-fn connect(points: &[Point], edges: &[(u128, usize, usize)], connection_limit: usize) -> usize {
-    let mut uf = UnionFind::new(points.len());
-    for (_, a, b) in edges.iter().copied().take(connection_limit) {
+/// Return the edge at zero-based rank `k` in the order produced by `sorted_edges` without sorting
+/// every pair. Useful for tuning `CONNECTIONS` against different inputs.
+#[cfg(test)]
+fn kth_closest<C: Coordinate>(
+    points: &[Point<C>],
+    k: usize,
+) -> Option<(C::Distance, usize, usize)> {
+    let mut edges = all_edges(points);
+    if k >= edges.len() {
+        return None;
+    }
+    let (_, &mut edge, _) = edges.select_nth_unstable_by_key(k, |&(dist, i, j)| (dist, i, j));
+    Some(edge)
+}
+
+/// Connect every given edge between `len` boxes and return the size of every resulting circuit,
+/// largest first.
+fn circuit_sizes<D>(len: usize, edges: impl IntoIterator<Item = (D, usize, usize)>) -> Vec<usize> {
+    let mut uf = UnionFind::new(len);
+    for (_, a, b) in edges {
         uf.union(a, b);
     }
 
     let mut sizes = uf.component_sizes();
     sizes.sort_unstable_by_key(|&size| Reverse(size));
-    debug_assert!(sizes.len() >= 3);
-    sizes.iter().take(3).product()
+    sizes
+}
+
+/// Connect the `limit` closest pairs of boxes and return the size of every resulting circuit,
+/// largest first.
+fn circuit_size_distribution<C: Coordinate>(points: &[Point<C>], limit: usize) -> Vec<usize> {
+    circuit_sizes(points.len(), sorted_edges(points).into_iter().take(limit))
+}
+
+/// Connect the first `limit` of the edges in order and count the boxes left in circuits of their
+/// own. Any left over suggest the limit is too low to connect every box.
+#[cfg(test)]
+fn singleton_count<C: Coordinate>(
+    points: &[Point<C>],
+    edges: &[(C::Distance, usize, usize)],
+    limit: usize,
+) -> usize {
+    let sizes = circuit_sizes(points.len(), edges.iter().copied().take(limit));
+    sizes.iter().filter(|&&size| size == 1).count()
+}
+
+/// Connect the `limit` closest pairs of boxes and multiply the sizes of the `top` largest circuits,
+/// or of every circuit if there are fewer. Also return how many of those connections joined boxes
+/// that already were in the same circuit.
+fn connect<C: Coordinate>(points: &[Point<C>], limit: usize, top: usize) -> (usize, usize) {
+    let sizes = circuit_size_distribution(points, limit);
+    // Every connection between two circuits merges them into one, so the rest were redundant
+    let num_pairs = points.len() * points.len().saturating_sub(1) / 2;
+    let redundant = limit.min(num_pairs) - (points.len() - sizes.len());
+    (sizes.iter().take(top).product(), redundant)
+}
+
+/// Connect every pair of boxes within squared distance `max_sq_dist` and multiply the sizes of the
+/// largest circuits.
+#[cfg(test)]
+fn connect_within<C: Coordinate>(points: &[Point<C>], max_sq_dist: C::Distance) -> usize {
+    let edges = sorted_edges(points)
+        .into_iter()
+        .take_while(|&(dist, _, _)| dist <= max_sq_dist);
+    circuit_sizes(points.len(), edges)
+        .iter()
+        .take(LARGEST_CIRCUITS)
+        .product()
 }
 
-fn final_connection(points: &[Point], edges: &[(u128, usize, usize)]) -> usize {
+/// Connect the edges in order and return the zero-based index of the connection after which the
+/// largest circuit first has at least as many boxes as each threshold, or `None` if it never does.
+/// Thresholds already met before any connection is made are reported at index 0.
+#[cfg(test)]
+fn growth_milestones<C: Coordinate>(
+    points: &[Point<C>],
+    edges: &[(C::Distance, usize, usize)],
+    thresholds: &[usize],
+) -> Vec<Option<usize>> {
     let mut uf = UnionFind::new(points.len());
-    let mut components = points.len();
-    for &(_, a, b) in edges {
+    let mut milestones: Vec<Option<usize>> = thresholds
+        .iter()
+        .map(|&threshold| (uf.max_size >= threshold).then_some(0))
+        .collect();
+    for (idx, &(_, a, b)) in edges.iter().enumerate() {
+        if !uf.union(a, b) {
+            continue;
+        }
+        for (milestone, &threshold) in milestones.iter_mut().zip(thresholds) {
+            if milestone.is_none() && uf.max_size >= threshold {
+                *milestone = Some(idx);
+            }
+        }
+    }
+    milestones
+}
+
+/// Connect the edges between `len` boxes in order and return the one that joins every box into a
+/// single circuit, or `None` if they never all get connected.
+fn completing_edge<D: Copy>(len: usize, edges: &[(D, usize, usize)]) -> Option<(D, usize, usize)> {
+    let mut uf = UnionFind::new(len);
+    let mut components = len;
+    for &(dist, a, b) in edges {
         let ra = uf.find(a);
         let rb = uf.find(b);
         if ra == rb {
@@ -149,27 +378,64 @@ fn final_connection(points: &[Point], edges: &[(u128, usize, usize)]) -> usize {
         uf.union(ra, rb);
         components -= 1;
         if components == 1 {
-            return points[a].x * points[b].x;
+            return Some((dist, a, b));
         }
     }
-    unreachable!("All points should eventually connect");
+    None
+}
+
+fn final_connection<C: Coordinate>(
+    points: &[Point<C>],
+    edges: &[(C::Distance, usize, usize)],
+) -> C {
+    let (_, a, b) =
+        completing_edge(points.len(), edges).expect("All points should eventually connect");
+    points[a].x * points[b].x
+}
+
+/// Return the squared length of the edge that completes the spanning tree when connecting the
+/// sorted edges in order. This is the longest edge of the minimum spanning tree, so every pair of
+/// boxes is joined by a path without any longer edge.
+#[cfg(test)]
+fn bottleneck_edge<C: Coordinate>(
+    points: &[Point<C>],
+    edges: &[(C::Distance, usize, usize)],
+) -> Option<C::Distance> {
+    completing_edge(points.len(), edges).map(|(dist, _, _)| dist)
+}
+
+/// Connect the 1000 closest pairs of boxes and multiply the `top` largest circuit sizes.
+fn part_a_top(boxes: &Boxes, top: usize) -> usize {
+    match boxes {
+        Boxes::Integer(points) => connect(points, CONNECTIONS, top).0,
+        Boxes::Decimal(points) => connect(points, CONNECTIONS, top).0,
+    }
 }
 
 /// Connect the 1000 closest pairs of boxes and multiply the three largest circuit sizes.
-fn part_a(points: &[Point]) -> usize {
-    let edges = sorted_edges(points);
-    connect(points, &edges, CONNECTIONS)
+pub fn part_a(boxes: &Boxes) -> usize {
+    part_a_top(boxes, LARGEST_CIRCUITS)
 }
 
 /// Multiply X coordinates of the final connection that joins all boxes.
-fn part_b(points: &[Point]) -> usize {
-    let edges = sorted_edges(points);
-    final_connection(points, &edges)
+pub fn part_b(boxes: &Boxes) -> Product {
+    match boxes {
+        Boxes::Integer(points) => Product::Integer(final_connection(points, &sorted_edges(points))),
+        Boxes::Decimal(points) => Product::Decimal(final_connection(points, &sorted_edges(points))),
+    }
+}
+
+/// Solve both parts, multiplying the `top` largest circuit sizes in part A instead of three.
+pub fn main_top(input: &str, top: usize) -> Result<(usize, Option<Product>)> {
+    if top == 0 {
+        bail!("Need at least one circuit to multiply");
+    }
+    let boxes = parse_input(input)?;
+    Ok((part_a_top(&boxes, top), Some(part_b(&boxes))))
 }
 
-pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
-    let points = parse_input(input)?;
-    Ok((part_a(&points), Some(part_b(&points))))
+pub fn main(input: &str) -> Result<(usize, Option<Product>)> {
+    main_top(input, LARGEST_CIRCUITS)
 }
 
 #[cfg(test)]
@@ -178,7 +444,7 @@ mod test {
 
     use super::*;
 
-    test_real_input!(8, 175_440, Some(3_200_955_921usize));
+    test_real_input!(8, 175_440, Some(Product::Integer(3_200_955_921)));
 
     const EXAMPLE_INPUT: &str = dedent!(
         r#"
@@ -207,14 +473,167 @@ mod test {
 
     #[test]
     fn example_a() {
-        let points = parse_input(EXAMPLE_INPUT).unwrap();
-        let edges = sorted_edges(&points);
-        assert_eq!(connect(&points, &edges, 10), 40);
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        assert_eq!(connect(&points, 10, LARGEST_CIRCUITS), (40, 1));
+    }
+
+    #[test]
+    fn top_circuits() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        // The largest circuits have 5, 4, 2 and 2 boxes, followed by single boxes
+        assert_eq!(connect(&points, 10, 2), (20, 1));
+        assert_eq!(connect(&points, 10, 1), (5, 1));
+        assert_eq!(connect(&points, 10, points.len()), (80, 1));
+        assert!(main_top(EXAMPLE_INPUT, 0).is_err());
     }
 
     #[test]
     fn example_b() {
-        let points = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_b(&points), 25_272);
+        let boxes = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part_b(&boxes), Product::Integer(25_272));
+    }
+
+    #[test]
+    fn size_distribution() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        assert_eq!(
+            circuit_size_distribution(&points, 10),
+            vec![5, 4, 2, 2, 1, 1, 1, 1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn singletons() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        let edges = sorted_edges(&points);
+        assert_eq!(singleton_count(&points, &edges, 0), 20);
+        assert_eq!(singleton_count(&points, &edges, 1), 18);
+        assert_eq!(singleton_count(&points, &edges, 10), 7);
+        assert_eq!(singleton_count(&points, &edges, edges.len()), 0);
+    }
+
+    #[test]
+    fn milestones() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        let edges = sorted_edges(&points);
+        assert_eq!(
+            growth_milestones(&points, &edges, &[2, 4, 8]),
+            vec![Some(0), Some(8), Some(14)]
+        );
+        // Every box is connected by the final connection of part B, and never more than that
+        assert_eq!(
+            growth_milestones(&points, &edges, &[1, 20, 21]),
+            vec![Some(0), Some(28), None]
+        );
+        let (_, a, b) = edges[28];
+        assert_eq!(points[a].x * points[b].x, 25_272);
+    }
+
+    #[test]
+    fn bottleneck() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        let edges = sorted_edges(&points);
+        // The final connection joins 216,146,977 and 117,168,530
+        assert_eq!(
+            bottleneck_edge(&points, &edges),
+            Some(99 * 99 + 22 * 22 + 447 * 447)
+        );
+        assert_eq!(bottleneck_edge(&points, &edges[..10]), None);
+        assert_eq!(bottleneck_edge(&points[..1], &[]), None);
+    }
+
+    #[test]
+    fn distance_cutoff() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        let edges = sorted_edges(&points);
+        let (tenth_closest, _, _) = edges[9];
+        assert!(edges[10].0 > tenth_closest);
+        assert_eq!(connect_within(&points, tenth_closest), 40);
+        assert_eq!(connect_within(&points, 0), 1);
+        assert_eq!(connect_within(&points, u128::MAX), points.len());
+    }
+
+    #[test]
+    fn decimal_coordinates() {
+        let halved: Vec<_> = EXAMPLE_INPUT
+            .lines()
+            .map(|line| {
+                line.split(',')
+                    .map(|value| format!("{:.1}", value.parse::<f64>().unwrap() / 2.0))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        let boxes = parse_input(&halved.join("\n")).unwrap();
+        let Boxes::Decimal(points) = &boxes else {
+            panic!("Expected decimal coordinates");
+        };
+        assert_eq!(connect(points, 10, LARGEST_CIRCUITS), (40, 1));
+        assert_eq!(part_b(&boxes), Product::Decimal(25_272.0 / 4.0));
+        assert!(parse_points::<f64>("1.5,2,NaN").is_err());
+        assert!(parse_points::<f64>("1.5,inf").is_err());
+    }
+
+    #[test]
+    fn reader() {
+        let boxes = parse_reader(EXAMPLE_INPUT.as_bytes()).unwrap();
+        assert_eq!(boxes, parse_input(EXAMPLE_INPUT).unwrap());
+
+        let boxes = parse_reader("1,2,3\n4.5,6,7\n8,9".as_bytes()).unwrap();
+        assert_eq!(boxes, parse_input("1,2,3\n4.5,6,7\n8,9").unwrap());
+        assert!(matches!(boxes, Boxes::Decimal(_)));
+
+        let err = parse_reader("1,2,3\n4,x,6".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn missing_z() {
+        let points = parse_points::<usize>("3,4\n0,0,0\n1,2,2").unwrap();
+        assert_eq!(points[0].z, 0);
+        assert_eq!(squared_distance(&points[0], &points[1]), 25);
+        assert_eq!(squared_distance(&points[1], &points[2]), 9);
+        assert!(parse_points::<usize>("1,2,3,4").is_err());
+        assert!(parse_points::<usize>("1").is_err());
+    }
+
+    #[test]
+    fn equal_distance_ties() {
+        let input = dedent!(
+            r#"
+                1,1
+                0,1
+                1,0
+                0,0
+            "#
+        );
+        let points = parse_points::<usize>(input).unwrap();
+        let edges = sorted_edges(&points);
+        assert_eq!(
+            edges,
+            vec![
+                (1, 0, 1),
+                (1, 0, 2),
+                (1, 1, 3),
+                (1, 2, 3),
+                (2, 0, 3),
+                (2, 1, 2),
+            ]
+        );
+
+        // Generation order must match the tie-break so a stable sort on distance alone agrees
+        let mut by_distance = all_edges(&points);
+        by_distance.sort_by_key(|&(dist, _, _)| dist);
+        assert_eq!(by_distance, edges);
+    }
+
+    #[test]
+    fn kth_closest_matches_sorted_edges() {
+        let points = parse_points::<usize>(EXAMPLE_INPUT).unwrap();
+        let edges = sorted_edges(&points);
+        for (k, &edge) in edges.iter().enumerate() {
+            assert_eq!(kth_closest(&points, k), Some(edge));
+        }
+        assert_eq!(kth_closest(&points, edges.len()), None);
     }
 }
@@ -6,19 +6,38 @@
 //!
 //! ## Part A
 //! Pick any two red tiles as opposite corners of an axis-aligned rectangle. Return the largest
-//! possible area, counting every tile inside the inclusive rectangle spanned by those corners.
+//! possible area, counting every tile inside the inclusive rectangle spanned by those corners. A
+//! single red tile spans a rectangle of area 1 and no red tiles give an area of 0.
 //!
 //! ## Part B
 //! Red corners still define the rectangle, but every tile it covers must be red or green (inside
-//! the perimeter). Find the largest possible area under this restriction.
+//! the perimeter). Find the largest possible area under this restriction. A variant allows the
+//! corners to be any tile on the perimeter instead of only the listed red tiles.
+//!
+//! A weighted variant follows the red tiles with a blank line and a grid of whitespace-separated
+//! tile values, one row per line. The area of a rectangle is then the sum of the values of the red
+//! or green tiles it covers instead of the number of tiles.
 use anyhow::{Context, Result, bail};
 
+use crate::parse;
+
+pub const TITLE: &str = "Movie Theater";
+
 #[derive(Debug, Clone, Copy)]
 struct Point {
     x: usize,
     y: usize,
 }
 
+/// Which tiles may be used as opposite corners of a rectangle
+#[derive(Debug, Clone, Copy)]
+enum Corners {
+    /// Only the listed red tiles
+    Vertices,
+    /// Any tile on the perimeter, including the green tiles between red ones
+    Perimeter,
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Rect {
     a: Point,
@@ -40,9 +59,29 @@ impl Rect {
         }
     }
 
-    /// Return the inclusive area of the rectangle.
-    fn area(&self) -> usize {
-        (self.b.x - self.a.x + 1) * (self.b.y - self.a.y + 1)
+    /// Check whether the rectangle is as wide as it's tall.
+    #[cfg(test)]
+    fn is_square(&self) -> bool {
+        self.b.x - self.a.x == self.b.y - self.a.y
+    }
+
+    /// Return the inclusive area of the rectangle, failing if it doesn't fit in a `usize`. With
+    /// `weights` the area is the sum of the values of the red or green tiles it covers instead.
+    fn area(&self, weights: Option<&WeightedArea>) -> Result<usize> {
+        if let Some(weights) = weights {
+            return Ok(weights.sum(self));
+        }
+        let width = (self.b.x - self.a.x).checked_add(1);
+        let height = (self.b.y - self.a.y).checked_add(1);
+        width
+            .zip(height)
+            .and_then(|(width, height)| width.checked_mul(height))
+            .with_context(|| {
+                format!(
+                    "Area of rectangle from {},{} to {},{} overflows",
+                    self.a.x, self.a.y, self.b.x, self.b.y
+                )
+            })
     }
 }
 
@@ -53,128 +92,452 @@ fn parse_input(input: &str) -> Result<Vec<Point>> {
         .lines()
         .enumerate()
         .map(|(idx, line)| {
-            let line_no = idx + 1;
-            let mut parts = line.split(',');
-            let x = parts
-                .next()
-                .context("Missing X coordinate")?
-                .parse()
-                .with_context(|| format!("Invalid X value on line {}", line_no))?;
-            let y = parts
-                .next()
-                .context("Missing Y coordinate")?
-                .parse()
-                .with_context(|| format!("Invalid Y value on line {}", line_no))?;
-
-            if parts.next().is_some() {
-                bail!("Too many comma-separated values on line {}", line_no);
-            }
-
+            let [x, y] = parse::fixed_tuple(line, idx + 1)?;
             Ok(Point { x, y })
         })
         .collect()
 }
 
-/// Return the largest possible rectangle area using any two red tiles as opposite corners.
-fn part_a(points: &[Point]) -> usize {
+/// Parse red tiles followed by a blank line and a grid of tile values indexed by `[y][x]`. Every
+/// red tile must be inside the grid.
+#[cfg(test)]
+fn parse_weighted_input(input: &str) -> Result<(Vec<Point>, TileValues)> {
+    let mut points = Vec::new();
+    let mut lines = input.trim().lines().enumerate();
+
+    // Red tiles end at the first blank line, every line after it is a row of tile values
+    for (idx, line) in &mut lines {
+        if line.trim().is_empty() {
+            break;
+        }
+        let [x, y] = parse::fixed_tuple(line, idx + 1)?;
+        points.push(Point { x, y });
+    }
+
+    let mut values: Vec<Vec<usize>> = Vec::new();
+    for (idx, line) in lines {
+        let row = line
+            .split_whitespace()
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid tile value '{value}' on line {}", idx + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(first) = values.first()
+            && first.len() != row.len()
+        {
+            bail!(
+                "Value row on line {} has {} tiles but the first row has {}",
+                idx + 1,
+                row.len(),
+                first.len()
+            );
+        }
+        values.push(row);
+    }
+
+    let values = TileValues { values };
+    if let Some(p) = points
+        .iter()
+        .find(|p| p.x >= values.width() || p.y >= values.height())
+    {
+        bail!(
+            "Red tile {},{} is outside the {}x{} value grid",
+            p.x,
+            p.y,
+            values.width(),
+            values.height()
+        );
+    }
+    Ok((points, values))
+}
+
+/// Return every rectangle spanned by a pair of red tiles, including a tile paired with itself.
+fn candidate_rects(points: &[Point]) -> impl Iterator<Item = Rect> + '_ {
     points
         .iter()
         .enumerate()
-        .flat_map(|(i, &a)| {
-            points
-                .iter()
-                .skip(i + 1)
-                .map(move |&b| Rect::new(a, b).area())
-        })
-        .max()
-        .unwrap_or(0)
+        .flat_map(|(i, &a)| points.iter().skip(i).map(move |&b| Rect::new(a, b)))
 }
 
-/// Return the largest rectangle that fits fully inside the green area with red opposite corners.
-fn part_b(points: &[Point]) -> Result<usize> {
-    let is_axis_aligned = points
+/// Return the largest area of the rectangles, or 0 if there are none.
+fn max_area(mut rects: impl Iterator<Item = Rect>) -> Result<usize> {
+    rects.try_fold(0, |best, rect| Ok(best.max(rect.area(None)?)))
+}
+
+/// Return the largest possible rectangle area using any two red tiles as opposite corners.
+fn part_a(points: &[Point]) -> Result<usize> {
+    max_area(candidate_rects(points))
+}
+
+/// Describe the vertical perimeter segments crossing scanline `y`. Only used to explain malformed
+/// loops, so it rescans the perimeter instead of slowing down the regular scan.
+fn crossing_segments(points: &[Point], y: usize) -> Vec<String> {
+    points
         .iter()
         .zip(points.iter().cycle().skip(1))
-        .all(|(a, b)| a.x == b.x || a.y == b.y);
-    if !is_axis_aligned {
-        bail!("Perimeter contains diagonal edge");
-    }
-    let min_y = points
-        .iter()
-        .map(|p| p.y)
-        .min()
-        .with_context(|| "Missing minimum Y value")?;
-    let max_y = points
-        .iter()
-        .map(|p| p.y)
-        .max()
-        .with_context(|| "Missing maximum Y value")?;
-    let height = max_y - min_y + 1;
-    let mut scanlines: Vec<Vec<usize>> = vec![Vec::new(); height];
-    let mut ranges_by_y: Vec<Vec<(usize, usize)>> = vec![Vec::new(); height];
-
-    for (&a, &b) in points.iter().zip(points.iter().cycle().skip(1)) {
-        if a.y == b.y {
-            let (x1, x2) = (a.x.min(b.x), a.x.max(b.x));
-            ranges_by_y[a.y - min_y].push((x1, x2));
-        } else if a.x == b.x {
-            let y_start = a.y.min(b.y);
-            let y_end = a.y.max(b.y);
-            for y in y_start..y_end {
-                scanlines[y - min_y].push(a.x);
+        .filter(|(a, b)| a.x == b.x && a.y.min(b.y) <= y && y < a.y.max(b.y))
+        .map(|(a, b)| format!("{},{}-{},{}", a.x, a.y, b.x, b.y))
+        .collect()
+}
+
+/// Merged ranges of red or green tiles for every row between the topmost and bottommost red tile.
+#[derive(Debug)]
+struct GreenArea {
+    bounds: Rect,
+    ranges_by_y: Vec<Vec<(usize, usize)>>,
+}
+
+impl GreenArea {
+    /// Scan the perimeter row by row to find the red or green ranges of each row.
+    fn new(points: &[Point]) -> Result<Self> {
+        let bounds = bounding_box(points).with_context(|| "Missing bounding box")?;
+        let (min_y, max_y) = (bounds.a.y, bounds.b.y);
+        let height = max_y - min_y + 1;
+        let mut scanlines: Vec<Vec<usize>> = vec![Vec::new(); height];
+        let mut ranges_by_y: Vec<Vec<(usize, usize)>> = vec![Vec::new(); height];
+
+        for (&a, &b) in points.iter().zip(points.iter().cycle().skip(1)) {
+            if a.y == b.y {
+                let (x1, x2) = (a.x.min(b.x), a.x.max(b.x));
+                ranges_by_y[a.y - min_y].push((x1, x2));
+            } else if a.x == b.x {
+                let y_start = a.y.min(b.y);
+                let y_end = a.y.max(b.y);
+                for y in y_start..y_end {
+                    scanlines[y - min_y].push(a.x);
+                }
+            }
+        }
+
+        for (offset, xs) in scanlines.into_iter().enumerate() {
+            let mut xs = xs;
+            xs.sort_unstable();
+            if !xs.len().is_multiple_of(2) {
+                let y = offset + min_y;
+                bail!(
+                    "Uneven number of intersections on scanline {y} at x = {xs:?} from segments {}",
+                    crossing_segments(points, y).join(", ")
+                );
+            }
+            for pair in xs.chunks_exact(2) {
+                ranges_by_y[offset].push((pair[0], pair[1]));
+            }
+        }
+
+        for ranges in &mut ranges_by_y {
+            ranges.sort_unstable_by_key(|&(start, _)| start);
+            let mut merged: Vec<(usize, usize)> = Vec::new();
+            for (start, end) in ranges.drain(..) {
+                if let Some((_, last_end)) = merged.last_mut()
+                    && start <= *last_end + 1
+                {
+                    *last_end = (*last_end).max(end);
+                    continue;
+                }
+                merged.push((start, end));
             }
+            *ranges = merged;
         }
+
+        Ok(Self {
+            bounds,
+            ranges_by_y,
+        })
+    }
+
+    /// Return the part of the rectangle that overlaps the bounding box, if any.
+    #[cfg(test)]
+    fn clip(&self, rect: &Rect) -> Option<Rect> {
+        let a = Point {
+            x: rect.a.x.max(self.bounds.a.x),
+            y: rect.a.y.max(self.bounds.a.y),
+        };
+        let b = Point {
+            x: rect.b.x.min(self.bounds.b.x),
+            y: rect.b.y.min(self.bounds.b.y),
+        };
+        (a.x <= b.x && a.y <= b.y).then_some(Rect { a, b })
+    }
+
+    /// Return the merged ranges of row `y`, which must be within the bounding box.
+    fn ranges(&self, y: usize) -> &[(usize, usize)] {
+        &self.ranges_by_y[y - self.bounds.a.y]
     }
 
-    for (offset, xs) in scanlines.into_iter().enumerate() {
-        let mut xs = xs;
-        xs.sort_unstable();
-        if xs.len() % 2 != 0 {
+    /// Count the tiles of the rectangle that are red or green. Tiles outside the bounding box are
+    /// never green, so the rectangle is clipped to it first.
+    #[cfg(test)]
+    fn area_within(&self, rect: &Rect) -> usize {
+        let Some(rect) = self.clip(rect) else {
+            return 0;
+        };
+        (rect.a.y..=rect.b.y)
+            .flat_map(|y| self.ranges(y))
+            .map(|&(start, end)| {
+                let (start, end) = (start.max(rect.a.x), end.min(rect.b.x));
+                if start <= end { end - start + 1 } else { 0 }
+            })
+            .sum()
+    }
+
+    /// Check whether every tile of the rectangle is red or green, failing if the rectangle reaches
+    /// outside the bounding box.
+    fn contains(&self, rect: &Rect) -> Result<bool> {
+        let Rect { a, b } = self.bounds;
+        if rect.a.x < a.x || rect.a.y < a.y || rect.b.x > b.x || rect.b.y > b.y {
             bail!(
-                "Uneven number of intersections on scanline {}",
-                offset + min_y
+                "Rectangle from {},{} to {},{} is outside the bounding box from {},{} to {},{}",
+                rect.a.x,
+                rect.a.y,
+                rect.b.x,
+                rect.b.y,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
             );
         }
-        for pair in xs.chunks_exact(2) {
-            ranges_by_y[offset].push((pair[0], pair[1]));
-        }
+        Ok((rect.a.y..=rect.b.y).all(|y| {
+            self.ranges(y)
+                .iter()
+                .any(|&(start, end)| start <= rect.a.x && rect.b.x <= end)
+        }))
     }
+}
+
+/// Value of every tile, indexed by `[y][x]`.
+#[derive(Debug)]
+struct TileValues {
+    values: Vec<Vec<usize>>,
+}
+
+impl TileValues {
+    fn width(&self) -> usize {
+        self.values.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.values.len()
+    }
+}
+
+/// Prefix sums over the values of the red or green tiles, so the weighted area of any rectangle
+/// takes constant time.
+#[derive(Debug)]
+struct WeightedArea {
+    /// Sum of the red or green tiles above and to the left of `[y][x]`, exclusive
+    prefix: Vec<Vec<usize>>,
+}
 
-    for ranges in &mut ranges_by_y {
-        ranges.sort_unstable_by_key(|&(start, _)| start);
-        let mut merged: Vec<(usize, usize)> = Vec::new();
-        for (start, end) in ranges.drain(..) {
-            if let Some((_, last_end)) = merged.last_mut()
-                && start <= *last_end + 1
-            {
-                *last_end = (*last_end).max(end);
-                continue;
+impl WeightedArea {
+    /// Sum up the values of the tiles that are red or green, where a missing green area means the
+    /// perimeter degenerated into a single segment covering its bounding box. Fails if the sum of
+    /// every such tile doesn't fit in a `usize`.
+    fn new(values: &TileValues, green: Option<&GreenArea>, points: &[Point]) -> Result<Self> {
+        let (width, height) = (values.width(), values.height());
+        let segment = bounding_box(points);
+        let mut prefix = vec![vec![0usize; width + 1]; height + 1];
+        for (y, row) in values.values.iter().enumerate() {
+            let ranges: Vec<(usize, usize)> = match (green, segment) {
+                (Some(green), _) if green.bounds.a.y <= y && y <= green.bounds.b.y => {
+                    green.ranges(y).to_vec()
+                }
+                (None, Some(Rect { a, b })) if a.y <= y && y <= b.y => vec![(a.x, b.x)],
+                _ => Vec::new(),
+            };
+            let mut row_sum = 0usize;
+            for (x, &value) in row.iter().enumerate() {
+                if ranges.iter().any(|&(start, end)| start <= x && x <= end) {
+                    row_sum = row_sum
+                        .checked_add(value)
+                        .context("Sum of tile values overflows")?;
+                }
+                prefix[y + 1][x + 1] = prefix[y][x + 1]
+                    .checked_add(row_sum)
+                    .context("Sum of tile values overflows")?;
             }
-            merged.push((start, end));
         }
-        *ranges = merged;
+        Ok(Self { prefix })
     }
 
-    let max_area = points
+    /// Return the sum of the values of the red or green tiles inside the rectangle. Tiles outside
+    /// the value grid are worth nothing.
+    fn sum(&self, rect: &Rect) -> usize {
+        let (width, height) = (self.prefix[0].len() - 1, self.prefix.len() - 1);
+        if rect.a.x >= width || rect.a.y >= height {
+            return 0;
+        }
+        let (x1, y1) = (rect.a.x, rect.a.y);
+        let (x2, y2) = (rect.b.x.min(width - 1) + 1, rect.b.y.min(height - 1) + 1);
+        // Subtract within each column first since prefix sums only grow to the right and down
+        (self.prefix[y2][x2] - self.prefix[y1][x2]) - (self.prefix[y2][x1] - self.prefix[y1][x1])
+    }
+}
+
+/// Return the smallest rectangle covering every red tile, or `None` if there are none.
+fn bounding_box(points: &[Point]) -> Option<Rect> {
+    let first = *points.first()?;
+    Some(points.iter().fold(Rect::new(first, first), |bounds, &p| {
+        Rect::new(
+            Point {
+                x: bounds.a.x.min(p.x),
+                y: bounds.a.y.min(p.y),
+            },
+            Point {
+                x: bounds.b.x.max(p.x),
+                y: bounds.b.y.max(p.y),
+            },
+        )
+    }))
+}
+
+/// Check whether the rectangle is fully red or green, where a missing green area means every tile
+/// of the degenerate perimeter is.
+fn is_green(green: Option<&GreenArea>, rect: &Rect) -> Result<bool> {
+    green.map_or(Ok(true), |green| green.contains(rect))
+}
+
+/// Scan the green area of a perimeter made of axis-aligned edges. Return `None` when the perimeter
+/// degenerates into a single segment where every tile is red or green.
+fn green_area(points: &[Point]) -> Result<Option<GreenArea>> {
+    let is_axis_aligned = points
         .iter()
-        .enumerate()
-        .flat_map(|(i, &a)| points.iter().skip(i + 1).map(move |&b| Rect::new(a, b)))
-        .filter(|rect| {
-            (rect.a.y..=rect.b.y).all(|y| {
-                ranges_by_y[y - min_y]
-                    .iter()
-                    .any(|&(start, end)| start <= rect.a.x && rect.b.x <= end)
+        .zip(points.iter().cycle().skip(1))
+        .all(|(a, b)| a.x == b.x || a.y == b.y);
+    if !is_axis_aligned {
+        bail!("Perimeter contains diagonal edge");
+    }
+
+    if points.len() <= 2 {
+        Ok(None)
+    } else {
+        GreenArea::new(points).map(Some)
+    }
+}
+
+/// Return every tile on the perimeter, walking each edge from one red tile up to the next. The
+/// perimeter must be axis-aligned.
+fn perimeter_tiles(points: &[Point]) -> Vec<Point> {
+    if points.len() == 1 {
+        return points.to_vec();
+    }
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .flat_map(|(&a, &b)| {
+            let len = a.x.abs_diff(b.x).max(a.y.abs_diff(b.y));
+            let step = |from: usize, to: usize, i: usize| {
+                if from <= to { from + i } else { from - i }
+            };
+            (0..len).map(move |i| Point {
+                x: if a.x == b.x { a.x } else { step(a.x, b.x, i) },
+                y: if a.y == b.y { a.y } else { step(a.y, b.y, i) },
             })
         })
-        .map(|rect| rect.area())
-        .max()
-        .unwrap_or(0);
-    Ok(max_area)
+        .collect()
 }
 
-pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
+/// Return the largest rectangle accepted by `keep` that fits fully inside the green area with
+/// opposite corners picked from `corners`.
+fn largest_green_rect(
+    points: &[Point],
+    corners: Corners,
+    keep: impl Fn(&Rect) -> bool,
+) -> Result<usize> {
+    let green = green_area(points)?;
+    // Walking the perimeter relies on `green_area` having rejected diagonal edges
+    let tiles = match corners {
+        Corners::Vertices => points.to_vec(),
+        Corners::Perimeter => perimeter_tiles(points),
+    };
+    candidate_rects(&tiles)
+        .filter(|rect| keep(rect))
+        .try_fold(0, |best, rect| {
+            if is_green(green.as_ref(), &rect)? {
+                Ok(best.max(rect.area(None)?))
+            } else {
+                Ok(best)
+            }
+        })
+}
+
+/// Count the red or green tiles inside the rectangle spanned by any two tiles as opposite corners.
+#[cfg(test)]
+fn green_area_between(points: &[Point], a: Point, b: Point) -> Result<usize> {
+    Ok(GreenArea::new(points)?.area_within(&Rect::new(a, b)))
+}
+
+/// Return the largest rectangle that fits fully inside the green area with red opposite corners.
+#[cfg(test)]
+fn part_b(points: &[Point]) -> Result<usize> {
+    part_b_corners(points, Corners::Vertices)
+}
+
+/// Return the largest rectangle that fits fully inside the green area with opposite corners picked
+/// from `corners`. Picking from the whole perimeter is quadratic in its length, so it's only meant
+/// for small inputs.
+fn part_b_corners(points: &[Point], corners: Corners) -> Result<usize> {
+    largest_green_rect(points, corners, |_| true)
+}
+
+/// Return the largest rectangle along with the largest one fitting fully inside the green area,
+/// scanning the green area once and visiting every pair of red corners in a single pass.
+fn solve(points: &[Point]) -> Result<(usize, usize)> {
+    solve_by(points, None)
+}
+
+/// Like `solve`, but measure each rectangle by the sum of the values of the red or green tiles it
+/// covers instead of by its number of tiles.
+#[cfg(test)]
+fn solve_weighted(points: &[Point], values: &TileValues) -> Result<(usize, usize)> {
+    solve_by(points, Some(values))
+}
+
+/// Return the largest rectangle along with the largest one fitting fully inside the green area,
+/// weighing tiles by `values` when given.
+fn solve_by(points: &[Point], values: Option<&TileValues>) -> Result<(usize, usize)> {
+    let green = green_area(points)?;
+    let weights = values
+        .map(|values| WeightedArea::new(values, green.as_ref(), points))
+        .transpose()?;
+    let mut best = 0;
+    let mut best_green = 0;
+    for rect in candidate_rects(points) {
+        let area = rect.area(weights.as_ref())?;
+        best = best.max(area);
+        if is_green(green.as_ref(), &rect)? {
+            best_green = best_green.max(area);
+        }
+    }
+    Ok((best, best_green))
+}
+
+/// Return the largest square that fits fully inside the green area with red opposite corners.
+#[cfg(test)]
+fn largest_green_square(points: &[Point]) -> Result<usize> {
+    largest_green_rect(points, Corners::Vertices, Rect::is_square)
+}
+
+/// Solve both parts, picking the opposite corners of part B's rectangles from `corners`.
+fn main_with(input: &str, corners: Corners) -> Result<(usize, Option<usize>)> {
     let points = parse_input(input)?;
-    Ok((part_a(&points), Some(part_b(&points)?)))
+    let (a, b) = match corners {
+        // Both parts pick red corners, so they're solved in a single pass
+        Corners::Vertices => solve(&points)?,
+        Corners::Perimeter => (part_a(&points)?, part_b_corners(&points, corners)?),
+    };
+    Ok((a, Some(b)))
+}
+
+/// Solve both parts, picking the opposite corners of part B's rectangles anywhere on the perimeter.
+pub fn main_perimeter_corners(input: &str) -> Result<(usize, Option<usize>)> {
+    main_with(input, Corners::Perimeter)
+}
+
+pub fn main(input: &str) -> Result<(usize, Option<usize>)> {
+    main_with(input, Corners::Vertices)
 }
 
 #[cfg(test)]
@@ -201,7 +564,7 @@ mod test {
     #[test]
     fn example_a() {
         let points = parse_input(EXAMPLE_INPUT).unwrap();
-        assert_eq!(part_a(&points), 50);
+        assert_eq!(part_a(&points).unwrap(), 50);
     }
 
     #[test]
@@ -210,6 +573,36 @@ mod test {
         assert_eq!(part_b(&points).unwrap(), 24);
     }
 
+    #[test]
+    fn perimeter_corners() {
+        let points = parse_input(EXAMPLE_INPUT).unwrap();
+        let tiles = perimeter_tiles(&points);
+        assert_eq!(tiles.len(), 30);
+        assert_eq!(part_b_corners(&points, Corners::Vertices).unwrap(), 24);
+        // From 2,3 to the middle of the right edge at 11,5
+        assert_eq!(part_b_corners(&points, Corners::Perimeter).unwrap(), 30);
+        assert_eq!(
+            main_perimeter_corners(EXAMPLE_INPUT).unwrap(),
+            (50, Some(30))
+        );
+
+        let points = parse_input("1,2\n1,5").unwrap();
+        assert_eq!(perimeter_tiles(&points).len(), 6);
+        assert_eq!(part_b_corners(&points, Corners::Perimeter).unwrap(), 4);
+        let points = parse_input("1,2").unwrap();
+        assert_eq!(part_b_corners(&points, Corners::Perimeter).unwrap(), 1);
+    }
+
+    #[test]
+    fn combined() {
+        let points = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(solve(&points).unwrap(), (50, 24));
+
+        let points = parse_input("1,2\n1,5").unwrap();
+        assert_eq!(solve(&points).unwrap(), (4, 4));
+        assert_eq!(solve(&[]).unwrap(), (0, 0));
+    }
+
     #[test]
     fn parses_single_coordinate() {
         let points = parse_input("1,2").unwrap();
@@ -217,4 +610,127 @@ mod test {
         assert_eq!(points[0].x, 1);
         assert_eq!(points[0].y, 2);
     }
+
+    #[test]
+    fn green_square() {
+        let points = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(largest_green_square(&points).unwrap(), 9);
+    }
+
+    #[test]
+    fn green_area() {
+        let points = parse_input(EXAMPLE_INPUT).unwrap();
+        let area = |a, b| green_area_between(&points, a, b).unwrap();
+        assert_eq!(area(Point { x: 0, y: 0 }, Point { x: 8, y: 6 }), 25);
+        assert_eq!(area(Point { x: 8, y: 6 }, Point { x: 0, y: 0 }), 25);
+        assert_eq!(area(Point { x: 2, y: 1 }, Point { x: 11, y: 7 }), 46);
+        assert_eq!(area(Point { x: 0, y: 0 }, Point { x: 20, y: 20 }), 46);
+        assert_eq!(area(Point { x: 3, y: 6 }, Point { x: 8, y: 8 }), 0);
+        assert_eq!(area(Point { x: 9, y: 5 }, Point { x: 11, y: 7 }), 9);
+    }
+
+    #[test]
+    fn outside_bounding_box() {
+        let points = parse_input(EXAMPLE_INPUT).unwrap();
+        let green = GreenArea::new(&points).unwrap();
+        assert!(green.contains(&Rect::new(points[0], points[4])).unwrap());
+
+        let rect = Rect::new(Point { x: 0, y: 0 }, Point { x: 8, y: 6 });
+        let err = green.contains(&rect).unwrap_err().to_string();
+        assert!(
+            err.contains("outside the bounding box from 2,1 to 11,7"),
+            "{err}"
+        );
+
+        let rect = Rect::new(Point { x: 9, y: 5 }, Point { x: 12, y: 7 });
+        assert!(green.contains(&rect).is_err());
+        assert_eq!(green.area_within(&rect), 9);
+        let rect = Rect::new(Point { x: 12, y: 8 }, Point { x: 20, y: 20 });
+        assert_eq!(green.area_within(&rect), 0);
+    }
+
+    #[test]
+    fn broken_loop() {
+        let points = parse_input("0,0\n4,0\n4,4").unwrap();
+        let err = GreenArea::new(&points).unwrap_err().to_string();
+        assert!(err.contains("scanline 0 at x = [4]"), "{err}");
+        assert!(err.contains("4,0-4,4"), "{err}");
+    }
+
+    #[test]
+    fn large_areas() {
+        let points = parse_input("0,0\n99999,0\n99999,99999\n0,99999").unwrap();
+        assert_eq!(part_a(&points).unwrap(), 10_000_000_000);
+        assert_eq!(part_b(&points).unwrap(), 10_000_000_000);
+
+        let points = parse_input("0,0\n4294967296,4294967296").unwrap();
+        let err = part_a(&points).unwrap_err();
+        assert!(err.to_string().contains("overflows"), "{err}");
+
+        let max = usize::MAX;
+        let points = parse_input(&format!("0,0\n{max},0")).unwrap();
+        assert!(part_a(&points).is_err());
+    }
+
+    #[test]
+    fn degenerate_inputs() {
+        let points = parse_input("").unwrap();
+        assert_eq!(part_a(&points).unwrap(), 0);
+        assert_eq!(part_b(&points).unwrap(), 0);
+
+        let points = parse_input("1,2").unwrap();
+        assert_eq!(part_a(&points).unwrap(), 1);
+        assert_eq!(part_b(&points).unwrap(), 1);
+
+        let points = parse_input("1,2\n1,5").unwrap();
+        assert_eq!(part_a(&points).unwrap(), 4);
+        assert_eq!(part_b(&points).unwrap(), 4);
+
+        let points = parse_input("1,2\n4,5").unwrap();
+        assert_eq!(part_a(&points).unwrap(), 16);
+        assert!(part_b(&points).is_err());
+    }
+
+    #[test]
+    fn weighted_tiles() {
+        let ones = ["1 1 1 1 1 1 1 1 1 1 1 1"; 8].join("\n");
+        let input = format!("{}\n\n{ones}", EXAMPLE_INPUT.trim());
+        let (points, values) = parse_weighted_input(&input).unwrap();
+        assert_eq!(points.len(), 8);
+        assert_eq!((values.width(), values.height()), (12, 8));
+        // Unit values count the red or green tiles, which only differs from the area in part A
+        // where the rectangle from 2,5 to 11,1 covers 40 of them
+        assert_eq!(solve_weighted(&points, &values).unwrap(), (40, 24));
+
+        let mut rows = ["1 1 1 1 1 1 1 1 1 1 1 1"; 8];
+        rows[4] = "1 1 1 50 1 1 1 1 1 1 1 1";
+        let input = format!("{}\n\n{}", EXAMPLE_INPUT.trim(), rows.join("\n"));
+        let (points, values) = parse_weighted_input(&input).unwrap();
+        assert_eq!(solve_weighted(&points, &values).unwrap(), (89, 73));
+
+        let green = GreenArea::new(&points).unwrap();
+        let weighted = WeightedArea::new(&values, Some(&green), &points).unwrap();
+        let area = |a, b| Rect::new(a, b).area(Some(&weighted)).unwrap();
+        assert_eq!(area(Point { x: 3, y: 4 }, Point { x: 3, y: 4 }), 50);
+        assert_eq!(area(Point { x: 0, y: 0 }, Point { x: 20, y: 20 }), 95);
+        assert_eq!(area(Point { x: 3, y: 6 }, Point { x: 8, y: 8 }), 0);
+        assert_eq!(area(Point { x: 12, y: 0 }, Point { x: 20, y: 20 }), 0);
+
+        let (points, values) =
+            parse_weighted_input("1,2\n1,5\n\n1 2\n3 4\n5 6\n7 8\n9 9\n1 1").unwrap();
+        assert_eq!(solve_weighted(&points, &values).unwrap(), (24, 24));
+
+        let err =
+            parse_weighted_input(&format!("{}\n\n1 1\n1 1", EXAMPLE_INPUT.trim())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Red tile 7,1 is outside the 2x2 value grid"
+        );
+        let err = parse_weighted_input("0,0\n\n1 1\n1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Value row on line 4 has 1 tiles but the first row has 2"
+        );
+        assert!(parse_weighted_input("0,0\n\n1 x").is_err());
+    }
 }
@@ -1,10 +1,12 @@
 #![deny(clippy::dbg_macro)]
 
 use anyhow::{Context as _, Result, anyhow};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::cmp::Reverse;
 use std::fs;
+use std::io;
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // Expose the test macro to the entire crate
 #[macro_use]
@@ -20,75 +22,383 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+mod output;
+mod parse;
 
-#[derive(Debug, Parser)]
-struct Options {
-    /// The day to run the solution for (1-25)
-    day: usize,
+use output::{Format, format_duration};
 
-    /// The input data file. Will look for `data/day<num>.txt` by default
-    input: Option<PathBuf>,
+/// Titles of all implemented days, sorted by day number
+const TITLES: &[(usize, &str)] = &[
+    (1, day1::TITLE),
+    (2, day2::TITLE),
+    (3, day3::TITLE),
+    (4, day4::TITLE),
+    (5, day5::TITLE),
+    (6, day6::TITLE),
+    (7, day7::TITLE),
+    (8, day8::TITLE),
+    (9, day9::TITLE),
+    (10, day10::TITLE),
+];
+
+/// A day's solution that renders its answers into a `DayResult`
+type Solver = fn(&str) -> Result<DayResult>;
+
+/// A day's solution that times parsing and each part separately
+type Profiler = fn(&str) -> Result<(DayResult, Profile)>;
+
+/// Time spent in each phase of a day's solution
+#[derive(Debug)]
+struct Profile {
+    parse: Duration,
+    part_a: Duration,
+    part_b: Duration,
 }
 
-fn run<F: FnOnce(&str) -> Result<(A, Option<B>)>, A: ToString, B: ToString>(
-    f: F,
-    input: &str,
-) -> Result<()> {
-    let start = Instant::now();
-    let (a, b) = f(input)?;
-    let time = Instant::now().saturating_duration_since(start);
+/// Answers of a single day's solution rendered as strings, along with how long it took to solve
+#[derive(Debug)]
+struct DayResult {
+    part_a: String,
+    part_b: Option<String>,
+    time: Duration,
+}
+
+impl DayResult {
+    /// Run a day's `main` on the given input, timing it and rendering the answers using `ToString`.
+    fn solve<A: ToString, B: ToString>(
+        f: impl FnOnce(&str) -> Result<(A, Option<B>)>,
+        input: &str,
+    ) -> Result<Self> {
+        let (answers, time) = timed(|| f(input));
+        let (a, b) = answers?;
 
-    println!("A: {}", pad_newlines(a.to_string()));
-    if let Some(b) = b {
-        println!("B: {}", pad_newlines(b.to_string()));
+        Ok(Self {
+            part_a: a.to_string(),
+            part_b: b.map(|b| b.to_string()),
+            time,
+        })
     }
-    println!();
-
-    let ns = time.as_nanos();
-    if ns < 10000 {
-        println!("Time: {ns} ns");
-    } else if ns < 1_000_000 {
-        println!("Time: {} µs", (ns + 500) / 1_000);
-    } else if ns < 1_000_000_000 {
-        println!("Time: {} ms", (ns + 500_000) / 1_000_000);
-    } else {
-        println!("Time: {:.3} s", time.as_secs_f64());
+
+    /// Run a day's parsing and both parts one by one, timing each phase separately.
+    fn profile<T, A: ToString, B: ToString>(
+        input: &str,
+        parse: impl FnOnce(&str) -> Result<T>,
+        part_a: impl FnOnce(&T) -> Result<A>,
+        part_b: impl FnOnce(&T) -> Result<B>,
+    ) -> Result<(Self, Profile)> {
+        let (parsed, parse_time) = timed(|| parse(input));
+        let parsed = parsed?;
+        let (a, part_a_time) = timed(|| part_a(&parsed));
+        let (b, part_b_time) = timed(|| part_b(&parsed));
+
+        let profile = Profile {
+            parse: parse_time,
+            part_a: part_a_time,
+            part_b: part_b_time,
+        };
+        let result = Self {
+            part_a: a?.to_string(),
+            part_b: Some(b?.to_string()),
+            time: parse_time + part_a_time + part_b_time,
+        };
+        Ok((result, profile))
     }
+}
 
-    Ok(())
+/// Run `f` and return its output along with how long it took.
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let output = f();
+    (output, Instant::now().saturating_duration_since(start))
 }
 
-fn pad_newlines(answer: String) -> String {
-    answer.lines().collect::<Vec<_>>().join("\n   ")
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Options {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The day to run the solution for (1-25)
+    #[arg(required_unless_present = "list")]
+    day: Option<usize>,
+
+    /// List all implemented days and their titles
+    #[arg(long, conflicts_with = "day")]
+    list: bool,
+
+    /// Time parsing and each part separately (only supported by some days)
+    #[arg(long)]
+    profile: bool,
+
+    /// Multiply the sizes of this many largest circuits instead of three (only supported by day 8)
+    #[arg(long, conflicts_with = "profile")]
+    top: Option<usize>,
+
+    /// Place the rolls on a hexagonal grid with six neighbors each (only supported by day 4)
+    #[arg(long, conflicts_with = "profile")]
+    hex: bool,
+
+    /// Evaluate problems without operands to their operation's identity (only supported by day 6)
+    #[arg(long, conflicts_with = "profile")]
+    keep_empty: bool,
+
+    /// Allow rectangle corners anywhere on the perimeter in part B (only supported by day 9)
+    #[arg(long, conflicts_with = "profile")]
+    perimeter_corners: bool,
+
+    /// Draw the input before the answers (only supported by day 7)
+    #[arg(long, conflicts_with_all = ["profile", "format"])]
+    render: bool,
+
+    /// Only print the answers, without timings or blank lines (JSON and CSV only print answers)
+    #[arg(long, conflicts_with = "profile")]
+    quiet: bool,
+
+    /// How to print the answers
+    #[arg(long, value_enum, default_value_t = Format::Human, conflicts_with = "profile")]
+    format: Format,
+
+    /// The input data file. Will look for `data/day<num>.txt` by default
+    input: Option<PathBuf>,
 }
 
-fn main() -> Result<()> {
-    let opts = Options::parse();
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run every implemented day and print a table sorted by solve time
+    Bench {
+        /// How many times to run each day; the table shows the average time
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        runs: u32,
+    },
+}
 
+/// Return the solver for the given day.
+fn solver(day: usize) -> Result<Solver> {
     #[allow(
         overlapping_range_endpoints,
         unreachable_patterns,
         clippy::match_overlapping_arm
     )]
-    let solution = match opts.day {
-        1 => day1::main,
-        2 => day2::main,
-        3 => day3::main,
-        4 => day4::main,
-        5 => day5::main,
-        6 => day6::main,
-        7 => day7::main,
-        8 => day8::main,
-        9 => day9::main,
-        10 => day10::main,
+    let solution: Solver = match day {
+        1 => |input| DayResult::solve(day1::main, input),
+        2 => |input| DayResult::solve(day2::main, input),
+        3 => |input| DayResult::solve(day3::main, input),
+        4 => |input| DayResult::solve(day4::main, input),
+        5 => |input| DayResult::solve(day5::main, input),
+        6 => |input| DayResult::solve(day6::main, input),
+        7 => |input| DayResult::solve(day7::main, input),
+        8 => |input| DayResult::solve(day8::main, input),
+        9 => |input| DayResult::solve(day9::main, input),
+        10 => |input| DayResult::solve(day10::main, input),
         day @ 1..=25 => return Err(anyhow!("No implementation for day {} yet", day)),
         day => return Err(anyhow!("Day {} is not a valid day for advent of code", day)),
     };
+    Ok(solution)
+}
+
+/// Return the path of the real input for the given day.
+fn default_input(day: usize) -> PathBuf {
+    format!("data/day{}.txt", day).into()
+}
+
+/// Run every implemented day `runs` times and print a table sorted by average solve time, slowest
+/// first. Days without an input file are listed as skipped.
+fn bench(runs: u32) -> Result<()> {
+    let mut solved = Vec::new();
+    let mut skipped = Vec::new();
+    for &(day, title) in TITLES {
+        let Ok(input) = fs::read_to_string(default_input(day)) else {
+            skipped.push((day, title));
+            continue;
+        };
+        let solution = solver(day)?;
+        let mut time = Duration::ZERO;
+        let mut result = None;
+        for _ in 0..runs {
+            let run = solution(&input).with_context(|| format!("Day {} failed", day))?;
+            time += run.time;
+            result = Some(run);
+        }
+        let result = result.context("No benchmark runs")?;
+        solved.push((day, title, result, time / runs));
+    }
+    solved.sort_by_key(|&(day, _, _, time)| (Reverse(time), day));
+
+    let header = ["Day", "Title", "A", "B", "Time"]
+        .map(String::from)
+        .to_vec();
+    let total: Duration = solved.iter().map(|&(_, _, _, time)| time).sum();
+    let rows: Vec<Vec<String>> = std::iter::once(header)
+        .chain(solved.into_iter().map(|(day, title, result, time)| {
+            vec![
+                day.to_string(),
+                title.to_string(),
+                result.part_a,
+                result.part_b.unwrap_or_default(),
+                format_duration(time),
+            ]
+        }))
+        .chain(skipped.into_iter().map(|(day, title)| {
+            vec![
+                day.to_string(),
+                title.to_string(),
+                String::new(),
+                String::new(),
+                "skipped".to_string(),
+            ]
+        }))
+        .chain(std::iter::once(vec![
+            "Total".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            format_duration(total),
+        ]))
+        .collect();
+    print!("{}", format_table(&rows));
+    Ok(())
+}
+
+/// Render rows as left-aligned columns padded to the widest cell of each column.
+fn format_table(rows: &[Vec<String>]) -> String {
+    let num_columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..num_columns)
+        .map(|col| {
+            rows.iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    rows.iter()
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+fn main() -> Result<()> {
+    let opts = Options::parse();
+    if let Some(Command::Bench { runs }) = opts.command {
+        return bench(runs);
+    }
+    if opts.list {
+        for (day, title) in TITLES {
+            println!("Day {day}: {title}");
+        }
+        return Ok(());
+    }
+    let day = opts.day.context("No day given")?;
+    let formatter = opts.format.formatter(opts.quiet);
 
-    let input_path = opts
-        .input
-        .unwrap_or_else(|| format!("data/day{}.txt", opts.day).into());
+    let solution = solver(day)?;
+
+    let input_path = opts.input.unwrap_or_else(|| default_input(day));
     let input = fs::read_to_string(&input_path)
         .with_context(|| format!("Failed to open input file {:?}", input_path))?;
-    run(solution, &input)
+
+    if opts.render {
+        let rendered = match day {
+            7 => day7::render(&input)?,
+            day => return Err(anyhow!("--render isn't supported for day {} yet", day)),
+        };
+        print!("{rendered}");
+    }
+
+    if !opts.profile {
+        let variants = [
+            (opts.top.is_some(), "--top", 8),
+            (opts.hex, "--hex", 4),
+            (opts.keep_empty, "--keep-empty", 6),
+            (opts.perimeter_corners, "--perimeter-corners", 9),
+        ];
+        for (enabled, flag, supported_day) in variants {
+            if enabled && day != supported_day {
+                return Err(anyhow!("{} isn't supported for day {} yet", flag, day));
+            }
+        }
+        let result = match opts.top {
+            Some(top) => DayResult::solve(|input| day8::main_top(input, top), &input)?,
+            None if opts.hex => DayResult::solve(day4::main_hex, &input)?,
+            None if opts.keep_empty => DayResult::solve(day6::main_keep_empty, &input)?,
+            None if opts.perimeter_corners => {
+                DayResult::solve(day9::main_perimeter_corners, &input)?
+            }
+            None => solution(&input)?,
+        };
+        formatter.emit(day, &result, result.time, &mut io::stdout())?;
+        return Ok(());
+    }
+
+    let profiler: Profiler = match day {
+        8 => |input| {
+            DayResult::profile(
+                input,
+                day8::parse_input,
+                |boxes| Ok(day8::part_a(boxes)),
+                |boxes| Ok(day8::part_b(boxes)),
+            )
+        },
+        10 => |input| {
+            DayResult::profile(
+                input,
+                day10::parse_input,
+                |machines| day10::part_a(machines),
+                |machines| day10::part_b(machines),
+            )
+        },
+        day => return Err(anyhow!("Profiling isn't supported for day {} yet", day)),
+    };
+    let (result, profile) = profiler(&input)?;
+    formatter.emit(day, &result, result.time, &mut io::stdout())?;
+    println!(
+        "parse: {}, part_a: {}, part_b: {}",
+        format_duration(profile.parse),
+        format_duration(profile.part_a),
+        format_duration(profile.part_b),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn output(result: &DayResult, quiet: bool) -> String {
+        let mut out = Vec::new();
+        Format::Human
+            .formatter(quiet)
+            .emit(1, result, result.time, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn table_alignment() {
+        let rows = vec![
+            vec!["Day".to_string(), "Time".to_string()],
+            vec!["10".to_string(), "5 ms".to_string()],
+            vec!["9".to_string(), "skipped".to_string()],
+        ];
+        assert_eq!(format_table(&rows), "Day  Time\n10   5 ms\n9    skipped\n");
+    }
+
+    #[test]
+    fn quiet() {
+        let result = DayResult::solve(day1::main, "L68\nL30\nR48").unwrap();
+        let answers = format!(
+            "A: {}\nB: {}\n",
+            result.part_a,
+            result.part_b.clone().unwrap()
+        );
+        assert_eq!(output(&result, true), answers);
+        assert!(output(&result, false).starts_with(&format!("{answers}\nTime: ")));
+    }
 }
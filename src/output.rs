@@ -0,0 +1,203 @@
+use crate::DayResult;
+use clap::ValueEnum;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Renders a day's answers and solve time
+pub trait OutputFormat {
+    /// Write the answers of `day` along with the time it took to solve to `out`.
+    fn emit(
+        &self,
+        day: usize,
+        result: &DayResult,
+        time: Duration,
+        out: &mut dyn Write,
+    ) -> io::Result<()>;
+}
+
+/// Output formats that can be selected on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One answer per line followed by the time taken
+    #[default]
+    Human,
+    /// A single JSON object
+    Json,
+    /// A header line followed by a single row of comma separated values
+    Csv,
+}
+
+impl Format {
+    /// Return the formatter for this format. `quiet` only applies to the human readable format.
+    pub fn formatter(self, quiet: bool) -> Box<dyn OutputFormat> {
+        match self {
+            Format::Human => Box::new(Human { quiet }),
+            Format::Json => Box::new(Json),
+            Format::Csv => Box::new(Csv),
+        }
+    }
+}
+
+/// Answers prefixed by their part, followed by a blank line and the time taken unless `quiet` is
+/// set. Continuation lines of multi-line answers are indented to line up with the first one.
+pub struct Human {
+    pub quiet: bool,
+}
+
+impl OutputFormat for Human {
+    fn emit(
+        &self,
+        _day: usize,
+        result: &DayResult,
+        time: Duration,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(out, "A: {}", pad_newlines(&result.part_a))?;
+        if let Some(b) = &result.part_b {
+            writeln!(out, "B: {}", pad_newlines(b))?;
+        }
+        if self.quiet {
+            return Ok(());
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Time: {}", format_duration(time))
+    }
+}
+
+/// A single JSON object with the day, both answers as strings and the time in nanoseconds
+pub struct Json;
+
+impl OutputFormat for Json {
+    fn emit(
+        &self,
+        day: usize,
+        result: &DayResult,
+        time: Duration,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let part_b = result
+            .part_b
+            .as_deref()
+            .map_or_else(|| "null".to_string(), json_string);
+        writeln!(
+            out,
+            r#"{{"day":{},"part_a":{},"part_b":{},"time_ns":{}}}"#,
+            day,
+            json_string(&result.part_a),
+            part_b,
+            time.as_nanos(),
+        )
+    }
+}
+
+/// A header line and a row with the day, both answers and the time in nanoseconds
+pub struct Csv;
+
+impl OutputFormat for Csv {
+    fn emit(
+        &self,
+        day: usize,
+        result: &DayResult,
+        time: Duration,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(out, "day,part_a,part_b,time_ns")?;
+        writeln!(
+            out,
+            "{},{},{},{}",
+            day,
+            csv_field(&result.part_a),
+            csv_field(result.part_b.as_deref().unwrap_or_default()),
+            time.as_nanos(),
+        )
+    }
+}
+
+/// Render a duration using the largest unit that still keeps a few significant digits.
+pub fn format_duration(time: Duration) -> String {
+    let ns = time.as_nanos();
+    if ns < 10000 {
+        format!("{ns} ns")
+    } else if ns < 1_000_000 {
+        format!("{} µs", (ns + 500) / 1_000)
+    } else if ns < 1_000_000_000 {
+        format!("{} ms", (ns + 500_000) / 1_000_000)
+    } else {
+        format!("{:.3} s", time.as_secs_f64())
+    }
+}
+
+fn pad_newlines(answer: &str) -> String {
+    answer.lines().collect::<Vec<_>>().join("\n   ")
+}
+
+/// Quote and escape a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn output(format: &dyn OutputFormat, result: &DayResult) -> String {
+        let mut out = Vec::new();
+        format.emit(6, result, result.time, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        let result = DayResult {
+            part_a: "#.\n.#".to_string(),
+            part_b: Some("say \"hi\", twice".to_string()),
+            time: Duration::from_micros(1500),
+        };
+        assert_eq!(
+            output(&Human { quiet: false }, &result),
+            "A: #.\n   .#\nB: say \"hi\", twice\n\nTime: 2 ms\n",
+        );
+        assert_eq!(
+            output(&Json, &result),
+            concat!(
+                "{\"day\":6,\"part_a\":\"#.\\n.#\",",
+                "\"part_b\":\"say \\\"hi\\\", twice\",\"time_ns\":1500000}\n",
+            ),
+        );
+        assert_eq!(
+            output(&Csv, &result),
+            "day,part_a,part_b,time_ns\n6,\"#.\n.#\",\"say \"\"hi\"\", twice\",1500000\n",
+        );
+
+        let result = DayResult {
+            part_b: None,
+            ..result
+        };
+        assert!(output(&Json, &result).contains("\"part_b\":null"));
+        assert!(output(&Csv, &result).ends_with("\n6,\"#.\n.#\",,1500000\n"));
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{Context, Result, bail};
+use std::str::FromStr;
+
+/// Names of the coordinate axes in the order they appear in a tuple.
+const AXES: [&str; 3] = ["X", "Y", "Z"];
+
+/// Parse a line of exactly `N` comma-separated numbers, like `x,y,z` coordinates. Errors name the
+/// axis of the offending value and the line it's on.
+pub fn fixed_tuple<T, const N: usize>(line: &str, line_no: usize) -> Result<[T; N]>
+where
+    T: FromStr + Default + Copy,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    const { assert!(N <= AXES.len()) };
+
+    let mut parts = line.split(',');
+    let mut values = [T::default(); N];
+    for (value, axis) in values.iter_mut().zip(AXES) {
+        *value = parts
+            .next()
+            .with_context(|| format!("Missing {axis} coordinate"))?
+            .parse()
+            .with_context(|| format!("Invalid {axis} value on line {}", line_no))?;
+    }
+
+    if parts.next().is_some() {
+        bail!("Too many comma-separated values on line {}", line_no);
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_tuples() {
+        assert_eq!(fixed_tuple::<usize, 2>("1,2", 1).unwrap(), [1, 2]);
+        assert_eq!(fixed_tuple::<usize, 3>("1,2,3", 1).unwrap(), [1, 2, 3]);
+        assert_eq!(fixed_tuple::<f64, 2>("1.5,2", 1).unwrap(), [1.5, 2.0]);
+    }
+
+    #[test]
+    fn error_messages() {
+        let err = |line| fixed_tuple::<usize, 3>(line, 7).unwrap_err().to_string();
+        assert_eq!(err("1"), "Missing Y coordinate");
+        assert_eq!(err("1,2,a"), "Invalid Z value on line 7");
+        assert_eq!(err("1,2,3,4"), "Too many comma-separated values on line 7");
+    }
+}